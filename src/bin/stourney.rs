@@ -1,6 +1,13 @@
 use clap::{Args, Parser, Subcommand};
 use clap_verbosity_flag::{Verbosity, WarnLevel};
-use stourney::{config, subcommands, utils};
+use log::error;
+use stourney::competitors::Competitor;
+use stourney::game::TimeControl;
+use stourney::results::ExportFormat;
+use stourney::stats::Sprt;
+use stourney::subcommands::SprtOutcome;
+use stourney::tournament::Seating;
+use stourney::{config, subcommands, update_check};
 
 pub use splendor_arena::tokio;
 
@@ -12,6 +19,10 @@ struct Cli {
     command: Option<MainCommands>,
     #[command(flatten)]
     verbose: Verbosity<WarnLevel>,
+    /// Skip checking for updates to stourney and to the scaffolding of
+    /// recent projects, as when the `offline` setting is true
+    #[arg(long, global = true)]
+    offline: bool,
 }
 
 #[derive(Subcommand)]
@@ -22,13 +33,186 @@ enum MainCommands {
     Version,
     /// Configure the stourney binary
     Config(ConfigArgs),
+    /// Check that everything needed to build and play the competitors is
+    /// installed and set up, suggesting a fix for every problem
+    Doctor,
     /// Run a competition locally
-    Run,
+    Run {
+        /// The number of games to play back to back, only with --headless,
+        /// the configured number of games by default
+        #[arg(short, long, requires = "headless")]
+        games: Option<usize>,
+        /// Play without the browser frontend, printing one line per game
+        /// and writing the results next to the config file
+        #[arg(long, conflicts_with = "frontend")]
+        headless: bool,
+        #[command(flatten)]
+        arena: ArenaArgs,
+    },
+    /// Run a round-robin tournament between the selected competitors
+    /// and print the final standings
+    Tournament {
+        /// The number of players in each game
+        #[arg(short, long, default_value_t = 2)]
+        players: usize,
+        /// The number of games to play for each combination of competitors,
+        /// or the number of rounds of seat orders when seats are rotated,
+        /// the configured number of games by default
+        #[arg(short, long)]
+        games: Option<usize>,
+        /// How to seat the competitors of each combination
+        #[arg(long, default_value = "fixed", value_parser = Seating::NAMES)]
+        seating: String,
+        /// Play without the browser frontend, printing one line per game
+        /// and writing the results next to the config file
+        #[arg(long, conflicts_with = "frontend")]
+        headless: bool,
+        #[command(flatten)]
+        arena: ArenaArgs,
+    },
+    /// Play the selected competitors against each other in every seat order,
+    /// so that no competitor benefits from moving first
+    Match {
+        /// The number of rounds of seat orders to play,
+        /// the configured number of games by default
+        #[arg(short, long)]
+        games: Option<usize>,
+        /// Whether to cycle the seats or play every possible seat order
+        #[arg(long, default_value = "permute", value_parser = Seating::NAMES)]
+        seating: String,
+        /// Play without the browser frontend, printing one line per game
+        /// and writing the results next to the config file
+        #[arg(long, conflicts_with = "frontend")]
+        headless: bool,
+        #[command(flatten)]
+        arena: ArenaArgs,
+    },
+    /// Play a candidate project against a base project, swapping seats,
+    /// until a sequential probability ratio test decides whether the candidate
+    /// is stronger. Exits with 0 if it is, 1 if it is not, 3 if the test
+    /// ran out of games and 2 on errors
+    Sprt {
+        /// The project to compare against
+        #[arg(long)]
+        base: String,
+        /// The project being tested
+        #[arg(long)]
+        candidate: String,
+        /// The Elo difference of the null hypothesis, that the candidate is no better
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        elo0: f64,
+        /// The Elo difference of the alternative hypothesis, that the candidate is better
+        #[arg(long, default_value_t = 5.0, allow_negative_numbers = true)]
+        elo1: f64,
+        /// The chance of accepting a candidate that is no better
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
+        /// The chance of rejecting a candidate that is better
+        #[arg(long, default_value_t = 0.05)]
+        beta: f64,
+        /// The number of games after which to give up without a decision
+        #[arg(long, default_value_t = 20_000)]
+        max_games: usize,
+        #[command(flatten)]
+        arena: ArenaArgs,
+    },
+    /// Show the ratings of every competitor that has played a rated game
+    Ratings,
+    /// List the games in the match history and the record of each competitor
+    History {
+        /// Only show games played by this project, given by path or directory name
+        #[arg(long)]
+        project: Option<String>,
+        /// Only show games also played by this project
+        #[arg(long)]
+        against: Option<String>,
+        /// Only show games finished within this long ago, such as 12h, 7d or 2w
+        #[arg(long)]
+        since: Option<String>,
+        /// The number of most recent games to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List, show and prune the replays of finished games
+    Replays {
+        #[command(subcommand)]
+        command: ReplaysCommands,
+    },
     /// Updates the projects that stourney knows about
     Update,
     /// Run and serve a game to global stourney server, where
     /// you can watch the game in real-time online
-    Watch,
+    Watch(ArenaArgs),
+    /// Launch a single arena from a serialized game setup,
+    /// used internally to run each game of a tournament
+    #[command(hide = true)]
+    Arena { setup: String },
+    /// Check for updates and cache the results,
+    /// used internally to check in the background
+    #[command(name = update_check::REFRESH_COMMAND, hide = true)]
+    RefreshUpdateCheck,
+}
+
+/// Overrides for the configured arena settings
+#[derive(Args)]
+struct ArenaArgs {
+    /// Play with the lineup and settings of a saved profile
+    #[arg(long)]
+    profile: Option<String>,
+    /// Use a preset time control
+    #[arg(long, value_parser = TimeControl::PRESETS)]
+    time_control: Option<String>,
+    /// The time each player starts the game with, in seconds
+    #[arg(long)]
+    initial_time: Option<f64>,
+    /// The time added to a player's clock after each move, in seconds
+    #[arg(long)]
+    increment: Option<f64>,
+    /// The port to serve the arena on, instead of the configured port
+    #[arg(long)]
+    port: Option<u16>,
+    /// The seed passed to the competitors in STOURNEY_SEED, chosen at random
+    /// if not given. It does not decide the deal yet, as the arena cannot be
+    /// seeded.
+    /// Each game of a batch uses the next seed after the previous game
+    #[arg(long)]
+    seed: Option<u64>,
+    /// The number of arenas to run at once when playing several games,
+    /// defaults to one per CPU
+    #[arg(short = 'j', long)]
+    workers: Option<usize>,
+    /// Export the results of every game to this file, with one row per game
+    /// and one row per player in each game
+    #[arg(long)]
+    results_out: Option<String>,
+    /// The format of the exported results, guessed from the file extension if not given
+    #[arg(long, requires = "results_out", value_parser = ExportFormat::NAMES)]
+    results_format: Option<String>,
+    /// Serve the frontend of this competitor or project, or `stourney`
+    /// for stourney's own copy, instead of the configured frontend
+    #[arg(long)]
+    frontend: Option<String>,
+    /// Play without the competitors that fail to build, instead of not playing at all
+    #[arg(long)]
+    skip_broken: bool,
+}
+
+impl From<ArenaArgs> for subcommands::ArenaOptions {
+    fn from(args: ArenaArgs) -> Self {
+        subcommands::ArenaOptions {
+            time_control: args.time_control,
+            initial_time: args.initial_time,
+            increment: args.increment,
+            port: args.port,
+            seed: args.seed,
+            headless: false,
+            workers: args.workers,
+            results_out: args.results_out,
+            results_format: args.results_format,
+            frontend: args.frontend,
+            skip_broken: args.skip_broken,
+        }
+    }
 }
 
 #[derive(Args)]
@@ -44,6 +228,100 @@ pub enum ConfigCommands {
     Edit,
     /// Show the current configuration
     Show,
+    /// Add a project as a named competitor
+    Add {
+        /// The project directory, optionally followed by @commit or @tag
+        path: String,
+        /// The name of the competitor, the name of the directory by default
+        #[arg(long)]
+        alias: Option<String>,
+        /// The language of the project, guessed from its contents by default
+        #[arg(long, value_parser = Competitor::LANGUAGES)]
+        language: Option<String>,
+    },
+    /// Remove a named competitor
+    Remove {
+        /// The name of the competitor
+        alias: String,
+    },
+    /// Change a setting, such as `port`, `workers` or `<alias>.args`
+    Set {
+        /// The setting to change
+        key: String,
+        /// The new value, empty to clear a competitor's optional setting.
+        /// A competitor's args are separated by whitespace, or given as a
+        /// JSON array of strings, such as '["--name", "Deep Blue"]', when
+        /// an argument contains whitespace
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+    /// Print the value of a setting
+    Get {
+        /// The setting to print
+        key: String,
+    },
+    /// Select the competitors to play, by name or project directory
+    Lineup {
+        #[arg(num_args = 2..=4, required = true)]
+        competitors: Vec<String>,
+    },
+    /// List, save and delete profiles, named lineups and settings
+    /// that are selected with --profile
+    Profiles {
+        #[command(subcommand)]
+        command: Option<ProfilesCommands>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfilesCommands {
+    /// List every profile and its settings
+    List,
+    /// Save a profile, replacing any profile with the same name
+    Save {
+        /// The name of the profile
+        name: String,
+        /// The competitors to play, by name or project directory,
+        /// the selected competitors by default
+        #[arg(long, num_args = 2..=4)]
+        lineup: Option<Vec<String>>,
+        /// Use a preset time control, the configured time control by default
+        #[arg(long, value_parser = TimeControl::PRESETS)]
+        time_control: Option<String>,
+        /// The number of games to play
+        #[arg(long)]
+        games: Option<usize>,
+        /// The number of arenas to run at once
+        #[arg(short = 'j', long)]
+        workers: Option<usize>,
+    },
+    /// Delete a profile
+    Delete {
+        /// The name of the profile
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReplaysCommands {
+    /// List every stored replay, oldest first
+    List,
+    /// Show every move of a replay
+    Show {
+        /// The id of the replay, or the start of it
+        id: String,
+    },
+    /// Step through a replay in the terminal
+    View {
+        /// The replay file, or the id of a stored replay
+        replay: String,
+    },
+    /// Delete all but the most recent replays
+    Prune {
+        /// The number of replays to keep
+        #[arg(long, default_value_t = 100)]
+        keep: usize,
+    },
 }
 
 #[tokio::main]
@@ -52,13 +330,46 @@ pub async fn main() {
     env_logger::Builder::new()
         .filter_level(args.verbose.log_level_filter())
         .init();
+
+    // Arenas are launched once per game by other stourney commands,
+    // which have already done the startup checks
+    if let Some(MainCommands::Arena { setup }) = &args.command {
+        subcommands::arena_command(setup).await;
+        return;
+    }
+
+    if let Some(MainCommands::RefreshUpdateCheck) = &args.command {
+        update_check::refresh();
+        return;
+    }
+
+    // The doctor reports an invalid config file rather than failing to start
+    if let Some(MainCommands::Doctor) = &args.command {
+        exit_on_failure(subcommands::doctor_command());
+        return;
+    }
+
     config::init_config();
+    if let Err(e) = config::workspace() {
+        error!("[-] {}", e);
+        std::process::exit(1);
+    }
     config::check_migration();
-    if !utils::out_of_date_projects().is_empty() {
-        println!("Some projects are out of date, run `stourney update` to update them");
+    if !args.offline && !config::get_config().offline {
+        update_check::warn_if_out_of_date();
     }
 
-    utils::check_for_updates();
+    let arena = match &args.command {
+        Some(MainCommands::Run { arena, .. })
+        | Some(MainCommands::Tournament { arena, .. })
+        | Some(MainCommands::Match { arena, .. })
+        | Some(MainCommands::Sprt { arena, .. })
+        | Some(MainCommands::Watch(arena)) => Some(arena),
+        _ => None,
+    };
+    if let Some(profile) = arena.and_then(|arena| arena.profile.as_deref()) {
+        exit_on_failure(config::select_profile(profile));
+    }
 
     match args.command {
         Some(MainCommands::New { directory }) => {
@@ -82,23 +393,177 @@ pub async fn main() {
                 ConfigCommands::Show => {
                     subcommands::show_competitors();
                 }
+                ConfigCommands::Add {
+                    path,
+                    alias,
+                    language,
+                } => exit_on_failure(subcommands::config_add_command(
+                    &path,
+                    alias.as_deref(),
+                    language.as_deref(),
+                )),
+                ConfigCommands::Remove { alias } => {
+                    exit_on_failure(subcommands::config_remove_command(&alias))
+                }
+                ConfigCommands::Set { key, value } => {
+                    exit_on_failure(subcommands::config_set_command(&key, &value))
+                }
+                ConfigCommands::Get { key } => {
+                    exit_on_failure(subcommands::config_get_command(&key))
+                }
+                ConfigCommands::Lineup { competitors } => {
+                    exit_on_failure(subcommands::config_lineup_command(&competitors))
+                }
+                ConfigCommands::Profiles { command } => match command {
+                    None | Some(ProfilesCommands::List) => subcommands::profiles_list_command(),
+                    Some(ProfilesCommands::Save {
+                        name,
+                        lineup,
+                        time_control,
+                        games,
+                        workers,
+                    }) => {
+                        let profile = config::Profile {
+                            lineup,
+                            time_control,
+                            games,
+                            workers,
+                            ..Default::default()
+                        };
+                        exit_on_failure(subcommands::profiles_save_command(&name, profile))
+                    }
+                    Some(ProfilesCommands::Delete { name }) => {
+                        exit_on_failure(subcommands::profiles_delete_command(&name))
+                    }
+                },
+            }
+        }
+
+        Some(MainCommands::Run {
+            games,
+            headless,
+            arena,
+        }) => {
+            let options = subcommands::ArenaOptions {
+                headless,
+                ..arena.into()
+            };
+            if headless {
+                exit_on_failure(subcommands::headless_command(games, &options));
+            } else {
+                exit_on_failure(subcommands::run_command(&options).await);
             }
         }
 
-        Some(MainCommands::Run) => {
-            subcommands::run_command().await;
+        Some(MainCommands::Tournament {
+            players,
+            games,
+            seating,
+            headless,
+            arena,
+        }) => {
+            let options = subcommands::ArenaOptions {
+                headless,
+                ..arena.into()
+            };
+            exit_on_failure(subcommands::tournament_command(
+                players, games, &seating, &options,
+            ));
+        }
+
+        Some(MainCommands::Match {
+            games,
+            seating,
+            headless,
+            arena,
+        }) => {
+            let options = subcommands::ArenaOptions {
+                headless,
+                ..arena.into()
+            };
+            exit_on_failure(subcommands::match_command(games, &seating, &options));
+        }
+
+        Some(MainCommands::Sprt {
+            base,
+            candidate,
+            elo0,
+            elo1,
+            alpha,
+            beta,
+            max_games,
+            arena,
+        }) => {
+            let sprt = Sprt {
+                elo0,
+                elo1,
+                alpha,
+                beta,
+            };
+            let outcome =
+                subcommands::sprt_command(&base, &candidate, sprt, max_games, &arena.into());
+            std::process::exit(match outcome {
+                SprtOutcome::Passed => 0,
+                SprtOutcome::Failed => 1,
+                SprtOutcome::Error => 2,
+                SprtOutcome::Inconclusive => 3,
+            });
+        }
+
+        Some(MainCommands::Ratings) => {
+            subcommands::ratings_command();
         }
 
+        Some(MainCommands::History {
+            project,
+            against,
+            since,
+            limit,
+        }) => {
+            subcommands::history_command(&subcommands::HistoryQuery {
+                project,
+                against,
+                since,
+                limit,
+            });
+        }
+
+        Some(MainCommands::Replays { command }) => match command {
+            ReplaysCommands::List => {
+                subcommands::replays_list_command();
+            }
+            ReplaysCommands::Show { id } => {
+                subcommands::replays_show_command(&id);
+            }
+            ReplaysCommands::View { replay } => {
+                subcommands::replays_view_command(&replay);
+            }
+            ReplaysCommands::Prune { keep } => {
+                subcommands::replays_prune_command(keep);
+            }
+        },
+
         Some(MainCommands::Update) => {
             subcommands::update_command();
         }
 
-        Some(MainCommands::Watch) => {
-            subcommands::watch_command().await;
+        Some(MainCommands::Watch(args)) => {
+            exit_on_failure(subcommands::watch_command(&args.into()).await);
         }
 
+        Some(MainCommands::Arena { .. })
+        | Some(MainCommands::RefreshUpdateCheck)
+        | Some(MainCommands::Doctor) => {}
+
         None => {
             println!("[-] Nothing to do, try running with --help");
         }
     }
 }
+
+/// Exits with a non-zero code if a command failed, so that scripts can tell
+fn exit_on_failure(succeeded: bool) {
+    if !succeeded {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,180 @@
+//! Contains the build cache, which skips building a competitor when nothing
+//! it is built from has changed since its last successful build. The
+//! fingerprint of every successful build is kept in a build manifest in
+//! the project directory
+use crate::constants;
+use crate::utils;
+use log::{info, trace, warn};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Something built in a project directory, cached separately
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// The binary of a rust project
+    Rust,
+    /// The python bindings of the scaffolding, installed in a python
+    /// project's virtual environment
+    Maturin,
+}
+
+impl Step {
+    fn name(&self) -> &'static str {
+        match self {
+            Step::Rust => "rust",
+            Step::Maturin => "maturin",
+        }
+    }
+}
+
+/// The fingerprints of the last successful builds of a project
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildManifest {
+    #[serde(default)]
+    fingerprints: BTreeMap<String, String>,
+}
+
+impl BuildManifest {
+    fn load(directory: &str) -> Self {
+        let path = Path::new(directory).join(constants::BUILD_MANIFEST_FILE_NAME);
+        let Ok(contents) = fs::read_to_string(path) else {
+            return BuildManifest::default();
+        };
+        serde_json::from_str(&contents).unwrap_or_default()
+    }
+
+    fn save(&self, directory: &str) {
+        let path = Path::new(directory).join(constants::BUILD_MANIFEST_FILE_NAME);
+        let contents = serde_json::to_string_pretty(self).expect("[-] Failed to serialize");
+        if let Err(e) = fs::write(&path, contents) {
+            warn!("[-] Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Builds the binary of the rust project in `directory`, unless its sources,
+/// Cargo.lock and scaffolding are unchanged since it was last built.
+/// Returns a summary of the compiler errors if the build failed
+pub fn build_rust(directory: &str) -> Result<(), String> {
+    let fingerprint = fingerprint(directory, Step::Rust);
+    if utils::rust_binary_path(directory).is_some() && is_fresh(directory, Step::Rust, &fingerprint)
+    {
+        info!("[+] {} is up to date", directory);
+        return Ok(());
+    }
+    utils::build_rust_project(directory)?;
+    // The sources as they were when the build started, so that changes made
+    // while it ran are built next time. Cargo writing Cargo.lock during the
+    // build only costs a rebuild that cargo itself finds nothing to do for
+    save(directory, Step::Rust, fingerprint);
+    Ok(())
+}
+
+/// Rebuilds the python bindings of the python project in `directory` unless
+/// they were last built successfully from its current scaffolding. Returns a
+/// summary of the errors if the rebuild failed, which is retried next time
+pub fn build_python(directory: &str) -> Result<(), String> {
+    let fingerprint = fingerprint(directory, Step::Maturin);
+    if is_fresh(directory, Step::Maturin, &fingerprint) {
+        info!("[+] {} is up to date", directory);
+        return Ok(());
+    }
+    println!("[+] Rebuilding the python bindings of {}...", directory);
+    utils::maturin_build(directory)?;
+    save(directory, Step::Maturin, fingerprint);
+    Ok(())
+}
+
+/// Records that `step` was just built in `directory` from its current sources
+pub fn record(directory: &str, step: Step) {
+    save(directory, step, fingerprint(directory, step));
+}
+
+fn is_fresh(directory: &str, step: Step, fingerprint: &str) -> bool {
+    let manifest = BuildManifest::load(directory);
+    manifest.fingerprints.get(step.name()).map(String::as_str) == Some(fingerprint)
+}
+
+fn save(directory: &str, step: Step, fingerprint: String) {
+    let mut manifest = BuildManifest::load(directory);
+    manifest
+        .fingerprints
+        .insert(step.name().to_string(), fingerprint);
+    manifest.save(directory);
+}
+
+/// Returns a hash of everything `step` is built from in `directory`: the
+/// commit and local changes of the scaffolding and, for a rust binary, every
+/// file of the project outside of its build output, scaffolding and virtual
+/// environment, which includes its sources, Cargo.toml and Cargo.lock
+fn fingerprint(directory: &str, step: Step) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(step.name());
+    hasher.update(utils::current_scaffolding_version(directory));
+    let scaffolding = Path::new(directory).join("lib").join("scaffolding");
+    if let Ok(diff) = Command::new("git")
+        .arg("diff")
+        .arg("HEAD")
+        .current_dir(scaffolding)
+        .output()
+    {
+        hasher.update(&diff.stdout);
+    }
+    if step == Step::Rust {
+        hash_files(&mut hasher, Path::new(directory), Path::new(directory));
+    }
+    let fingerprint: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    trace!(
+        "Fingerprint of {} in {}: {}",
+        step.name(),
+        directory,
+        fingerprint
+    );
+    fingerprint
+}
+
+/// The directories of a project that are not built from
+const IGNORED_DIRECTORIES: [&str; 3] = ["target", "venv", "lib"];
+
+/// Hashes the path and contents of every file under `directory`, in a fixed
+/// order, skipping git's files, the build manifest and the ignored
+/// directories of the project
+fn hash_files(hasher: &mut Sha1, project: &Path, directory: &Path) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name == ".git" || name == constants::BUILD_MANIFEST_FILE_NAME {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if directory == project && IGNORED_DIRECTORIES.contains(&name.as_ref()) {
+                continue;
+            }
+            hash_files(hasher, project, &path);
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(project).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            if let Ok(contents) = fs::read(&path) {
+                hasher.update((contents.len() as u64).to_le_bytes());
+                hasher.update(&contents);
+            }
+        }
+    }
+}
@@ -0,0 +1,634 @@
+//! Contains the named competitors of the config file and competitor specs,
+//! which name either the working tree of a project or the project at a git
+//! revision, written `path@commit` or `path@tag`. Revisions are checked out
+//! into cached worktrees next to the config file
+use crate::builds;
+use crate::config;
+use crate::constants;
+use crate::manifest::ProjectManifest;
+use crate::utils;
+use log::{error, info, trace};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// A named competitor in the config file, which is how it is shown in
+/// standings, ratings, replays and results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Competitor {
+    /// The short, unique name of the competitor
+    pub alias: String,
+    /// The full path to the project directory
+    pub path: String,
+    /// The language of the project, `rust` or `python`,
+    /// guessed from the project directory if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The git commit or tag to play, or the working tree if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Extra arguments passed to the competitor, before `--port`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Extra environment variables set for the competitor
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Competitor {
+    /// The languages a competitor can be written in
+    pub const LANGUAGES: [&'static str; 2] = ["rust", "python"];
+
+    /// Returns the project and revision this competitor plays
+    pub fn spec(&self) -> Spec {
+        Spec {
+            path: self.path.clone(),
+            revision: self.commit.clone(),
+        }
+    }
+
+    /// Returns the type of the project checked out in `directory`,
+    /// as configured or otherwise guessed from its contents
+    pub fn project_type(&self, directory: &str) -> utils::ProjectType {
+        match self.language.as_deref() {
+            Some("rust") => utils::ProjectType::Rust,
+            Some("python") => utils::ProjectType::Python,
+            Some(_) => utils::ProjectType::Unknown,
+            None => utils::guess_project_type(directory),
+        }
+    }
+
+    /// Returns the program the arena should launch for this competitor. Without
+    /// extra arguments or environment variables that is the competitor's own
+    /// binary, otherwise it is a launcher that adds them before running it
+    pub fn launcher(&self, binary: &str) -> Option<String> {
+        if self.args.is_empty() && self.env.is_empty() {
+            return Some(binary.to_string());
+        }
+        self.write_launcher(binary, None, None)
+    }
+
+    /// Returns the program the arena should launch for this competitor's python
+    /// bot, a launcher that runs it with `interpreter`. The arena would run
+    /// every python bot with the same interpreter, and so the same packages
+    pub fn python_launcher_with(&self, interpreter: &str, binary: &str) -> Option<String> {
+        let run = if cfg!(target_os = "windows") {
+            format!("\"{}\"", interpreter)
+        } else {
+            shell_quote(interpreter)
+        };
+        self.write_launcher(binary, Some(&run), None)
+    }
+
+    /// Returns the program the arena should launch for this competitor when its
+    /// project in `directory` has a manifest, a launcher that runs the entry
+    /// point with the manifest's run command from its working directory
+    pub fn manifest_launcher(&self, manifest: &ProjectManifest, directory: &str) -> Option<String> {
+        let entry_point = manifest.entry_point(directory);
+        let working_directory = manifest.working_directory(directory);
+        self.write_launcher(
+            entry_point.to_str()?,
+            manifest.run.as_deref(),
+            working_directory.to_str(),
+        )
+    }
+
+    /// Writes a launcher script that runs `binary` with this competitor's
+    /// arguments and environment, with `run` if it is given, from `directory`
+    /// if it is given, and returns its path. Launchers are named by a hash of
+    /// their contents, so runs in other workspaces or with other settings
+    /// never overwrite a launcher that is in use
+    fn write_launcher(
+        &self,
+        binary: &str,
+        run: Option<&str>,
+        directory: Option<&str>,
+    ) -> Option<String> {
+        let launchers = config::config_directory().join(constants::LAUNCHERS_DIRECTORY);
+        if let Err(e) = fs::create_dir_all(&launchers) {
+            error!("[-] Failed to create {}: {}", launchers.display(), e);
+            return None;
+        }
+        // The arena runs files ending in .py with the python interpreter,
+        // so python competitors get a python launcher
+        let (extension, script) = if binary.ends_with(".py") && run.is_none() {
+            ("py", self.python_launcher(binary, directory))
+        } else if cfg!(target_os = "windows") {
+            ("cmd", self.batch_launcher(binary, run, directory))
+        } else {
+            ("sh", self.shell_launcher(binary, run, directory))
+        };
+        let hash: String = Sha1::digest(&script)
+            .iter()
+            .take(6)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let name = format!("{}-{}.{}", self.alias, hash, extension);
+        let path = launchers.join(&name);
+        if path.exists() {
+            trace!("Launching {} with {}", self.alias, path.display());
+            return Some(path.to_str()?.to_string());
+        }
+
+        // Written next to the launcher and renamed into place, so that a
+        // concurrent run never launches a partly written script
+        let partial = launchers.join(format!("{}.{}.partial", name, std::process::id()));
+        if let Err(e) = fs::write(&partial, script) {
+            error!("[-] Failed to write {}: {}", partial.display(), e);
+            return None;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let executable = fs::Permissions::from_mode(0o755);
+            if let Err(e) = fs::set_permissions(&partial, executable) {
+                error!("[-] Failed to make {} executable: {}", partial.display(), e);
+                let _ = fs::remove_file(&partial);
+                return None;
+            }
+        }
+        if let Err(e) = fs::rename(&partial, &path) {
+            error!("[-] Failed to write {}: {}", path.display(), e);
+            let _ = fs::remove_file(&partial);
+            return None;
+        }
+        trace!("Launching {} with {}", self.alias, path.display());
+        Some(path.to_str()?.to_string())
+    }
+
+    fn python_launcher(&self, binary: &str, directory: Option<&str>) -> String {
+        // JSON strings are also valid python strings
+        let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+        let env: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}: {}", quote(key), quote(value)))
+            .collect();
+        let args: Vec<String> = self.args.iter().map(|arg| quote(arg)).collect();
+        let chdir = match directory {
+            Some(directory) => format!("os.chdir({})\n", quote(directory)),
+            None => String::new(),
+        };
+        format!(
+            "import os, runpy, sys\n\
+             bot = {bot}\n\
+             os.environ.update({{{env}}})\n\
+             sys.argv = [bot, {args}] + sys.argv[1:]\n\
+             sys.path.insert(0, os.path.dirname(bot))\n\
+             {chdir}\
+             runpy.run_path(bot, run_name=\"__main__\")\n",
+            bot = quote(binary),
+            env = env.join(", "),
+            args = args.join(", "),
+        )
+    }
+
+    fn shell_launcher(&self, binary: &str, run: Option<&str>, directory: Option<&str>) -> String {
+        let quote = shell_quote;
+        let mut script = String::from("#!/bin/sh\n");
+        for (key, value) in &self.env {
+            script += &format!("export {}={}\n", key, quote(value));
+        }
+        if let Some(directory) = directory {
+            script += &format!("cd {} || exit 1\n", quote(directory));
+        }
+        // The run command is a shell command of its own, so it is not quoted
+        match run {
+            Some(run) => script += &format!("exec {} {}", run, quote(binary)),
+            None => script += &format!("exec {}", quote(binary)),
+        }
+        for arg in &self.args {
+            script += &format!(" {}", quote(arg));
+        }
+        script + " \"$@\"\n"
+    }
+
+    fn batch_launcher(&self, binary: &str, run: Option<&str>, directory: Option<&str>) -> String {
+        let mut script = String::from("@echo off\r\n");
+        for (key, value) in &self.env {
+            script += &format!("set {}={}\r\n", batch_escape(key), batch_escape(value));
+        }
+        if let Some(directory) = directory {
+            script += &format!("cd /d {} || exit /b 1\r\n", batch_quote(directory));
+        }
+        // The run command is a command of its own, so it is not quoted
+        if let Some(run) = run {
+            script += &format!("{} ", run);
+        }
+        script += &batch_quote(binary);
+        for arg in &self.args {
+            script += &format!(" {}", batch_quote(arg));
+        }
+        script + " %*\r\n"
+    }
+}
+
+/// Quotes a value as a single argument of a command in a batch file. The
+/// value is quoted the way programs split their command line, then every
+/// character cmd treats specially is escaped, quotes included, so that cmd
+/// passes the quoted value through untouched
+fn batch_quote(value: &str) -> String {
+    // Backslashes are only special right before a quote
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted += &"\\".repeat(backslashes + 1);
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        quoted.push(c);
+    }
+    quoted += &"\\".repeat(backslashes);
+    quoted.push('"');
+    batch_escape(&quoted)
+}
+
+/// Escapes the characters cmd treats specially in a line of a batch file
+fn batch_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            // Carets do not escape percent signs in batch files
+            '%' => escaped += "%%",
+            '^' | '"' | '&' | '|' | '<' | '>' | '(' | ')' => {
+                escaped.push('^');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes a value as a single word of a posix shell command
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+impl fmt::Display for Competitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.alias, self.spec())
+    }
+}
+
+/// Returns whether an alias can name a competitor. Aliases are used in file
+/// names and on the command line, so they are kept to a single plain word
+pub fn valid_alias(alias: &str) -> bool {
+    !alias.is_empty()
+        && alias
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+/// Returns an alias for the given project that no competitor in `taken` uses,
+/// its directory name followed by its revision, if any
+pub fn unique_alias(spec: &Spec, taken: &[Competitor]) -> String {
+    let mut base: String = utils::project_name(&spec.path)
+        .chars()
+        .map(|c| if valid_alias(&c.to_string()) { c } else { '_' })
+        .collect();
+    if let Some(revision) = &spec.revision {
+        base = format!("{}@{}", base, revision.replace(['/', '\\'], "_"));
+    }
+    let free = |alias: &str| !taken.iter().any(|competitor| competitor.alias == alias);
+    if free(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|alias| free(alias))
+        .unwrap_or(base)
+}
+
+/// Finds the competitor a user asked for: a named competitor from the config
+/// file, or a project path, optionally pinned with `@revision`, which is given
+/// the alias of the named competitor playing it, or a new one
+pub fn resolve(name: &str) -> Option<Competitor> {
+    let cfg = config::get_config();
+    if let Some(competitor) = cfg.competitors.iter().find(|c| c.alias == name) {
+        return Some(competitor.clone());
+    }
+    let spec = Spec::parse(name);
+    if !Path::new(&spec.path).exists() {
+        error!("[-] No competitor named {}", name);
+        error!("[-] Try `stourney config show` to list the competitors");
+        return None;
+    }
+    let spec = spec.full();
+    if let Some(competitor) = cfg.competitors.iter().find(|c| c.spec() == spec) {
+        return Some(competitor.clone());
+    }
+    Some(Competitor {
+        alias: unique_alias(&spec, &cfg.competitors),
+        path: spec.path,
+        language: None,
+        commit: spec.revision,
+        args: Vec::new(),
+        env: BTreeMap::new(),
+    })
+}
+
+/// A competitor as written in the config file or on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    /// The directory of the project
+    pub path: String,
+    /// The git revision to play, or `None` for the working tree
+    pub revision: Option<String>,
+}
+
+impl Spec {
+    /// Splits a competitor into its project directory and revision. Only the
+    /// last `@` counts, and only if no path separator follows it, so that
+    /// directories containing an `@` still work
+    pub fn parse(competitor: &str) -> Self {
+        match competitor.rsplit_once('@') {
+            Some((path, revision))
+                if !path.is_empty() && !revision.is_empty() && !revision.contains(['/', '\\']) =>
+            {
+                Spec {
+                    path: path.to_string(),
+                    revision: Some(revision.to_string()),
+                }
+            }
+            _ => Spec {
+                path: competitor.to_string(),
+                revision: None,
+            },
+        }
+    }
+
+    /// Returns whether the project is valid and, if the spec is pinned,
+    /// whether its revision exists
+    pub fn check(&self, verbose: bool) -> bool {
+        if !utils::check_project(&self.path, verbose) {
+            return false;
+        }
+        match &self.revision {
+            Some(revision) if rev_parse(&self.path, revision).is_none() => {
+                if verbose {
+                    error!(
+                        "[-] Revision {} not found in the git repository at {}",
+                        revision, self.path
+                    );
+                }
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the same spec with the full path to its project, which must exist
+    pub fn full(&self) -> Self {
+        Spec {
+            path: utils::relative_to_full_path(&self.path),
+            revision: self.revision.clone(),
+        }
+    }
+
+    /// Returns the commit this spec plays: its revision if it is pinned,
+    /// otherwise the commit its working tree is at
+    pub fn commit(&self) -> Option<String> {
+        match &self.revision {
+            Some(revision) => rev_parse(&self.path, revision),
+            None => utils::git_commit(&self.path),
+        }
+    }
+
+    /// Returns the directory to build and run this competitor from. The working
+    /// tree is used as it is, while a revision is checked out into a cached
+    /// worktree and prepared like a new project the first time it is played
+    pub fn checkout(&self) -> Option<String> {
+        let Some(revision) = &self.revision else {
+            return Some(self.path.clone());
+        };
+        let Some(commit) = rev_parse(&self.path, revision) else {
+            error!(
+                "[-] Revision {} not found in the git repository at {}",
+                revision, self.path
+            );
+            return None;
+        };
+        let (Some(root), Some(prefix)) = (
+            git_path(&self.path, "--show-toplevel"),
+            git_path(&self.path, "--show-prefix"),
+        ) else {
+            error!("[-] Failed to find the git repository of {}", self.path);
+            return None;
+        };
+        // Worktrees check out the whole repository, so projects that share
+        // a repository also share the worktree of each of its commits
+        let directory = config::config_directory()
+            .join(constants::WORKTREES_DIRECTORY)
+            .join(format!(
+                "{}-{}",
+                utils::project_name(&root),
+                &commit[..commit.len().min(12)]
+            ));
+        let worktree = directory.to_str()?.to_string();
+
+        if !directory.exists() {
+            println!("[+] Checking out {} into {}...", self, worktree);
+            let added = Command::new("git")
+                .arg("worktree")
+                .arg("add")
+                .arg("--detach")
+                .arg(&worktree)
+                .arg(&commit)
+                .current_dir(&self.path)
+                .output();
+            match added {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => {
+                    error!("[-] Failed to check out {}", self);
+                    error!("{}", String::from_utf8_lossy(&output.stderr).trim());
+                    return None;
+                }
+                Err(e) => {
+                    error!("[-] Failed to check out {}: {}", self, e);
+                    return None;
+                }
+            }
+        }
+        let project = directory.join(prefix);
+        let project = project.to_str()?.trim_end_matches(['/', '\\']).to_string();
+        if !Path::new(&project).is_dir() {
+            error!("[-] {} has no project at revision {}", self.path, revision);
+            return None;
+        }
+        prepare(&self.path, &project)?;
+        trace!("Playing {} from {}", self, project);
+        Some(project)
+    }
+}
+
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.revision {
+            Some(revision) => write!(f, "{}@{}", self.path, revision),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// Returns a path `git rev-parse` gives for the repository at `path`, such as
+/// its root with `--show-toplevel` or the path of `path` within it with
+/// `--show-prefix`, which is empty at the root
+fn git_path(path: &str, option: &str) -> Option<String> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg(option)
+        .current_dir(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns the full commit hash a revision of the repository at `path` points to
+fn rev_parse(path: &str, revision: &str) -> Option<String> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("{}^{{commit}}", revision))
+        .current_dir(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Fills in whatever a worktree of the project at `path` is missing to be
+/// played. The scaffolding is usually not committed, so the project's own
+/// is shared, and python projects get a virtual environment of their own.
+/// A shared scaffolding is at whatever commit the project's is at now, not
+/// the one the revision was played with, so a pinned revision is built and
+/// run against the current scaffolding unless it commits its own
+fn prepare(path: &str, worktree: &str) -> Option<()> {
+    let lib = Path::new(worktree).join("lib");
+    let shared = Path::new(path).join("lib");
+    if !lib.exists() && shared.exists() && link(&shared, &lib).is_err() {
+        error!(
+            "[-] Failed to share the scaffolding of {} with {}",
+            path, worktree
+        );
+        return None;
+    }
+
+    let interpreter = utils::python_interpreter_path(worktree);
+    if matches!(
+        utils::guess_project_type(worktree),
+        utils::ProjectType::Python
+    ) && !Path::new(&interpreter).exists()
+    {
+        println!("[+] Setting up the virtual environment of {}...", worktree);
+        let venv = Path::new(worktree).join("venv");
+        if !utils::setup_venv(venv.to_str()?) {
+            return None;
+        }
+        if let Err(summary) = utils::maturin_build(worktree) {
+            error!(
+                "[-] Failed to build the python bindings of {}:\n{}",
+                worktree, summary
+            );
+            return None;
+        }
+        builds::record(worktree, builds::Step::Maturin);
+        info!("[+] Worktree {} is ready", worktree);
+    }
+    Some(())
+}
+
+#[cfg(unix)]
+fn link(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn link(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(original, link)
+}
+
+/// Returns the directory whose virtual environment runs this competitor's
+/// python. Rust worktrees have no virtual environment of their own,
+/// so they use the one of the project they were checked out from
+pub fn interpreter_directory(spec: &Spec, directory: &str) -> String {
+    if Path::new(&utils::python_interpreter_path(directory)).exists() {
+        directory.to_string()
+    } else {
+        spec.path.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(path: &str, revision: Option<&str>) -> Spec {
+        Spec {
+            path: path.to_string(),
+            revision: revision.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parse_splits_off_the_revision() {
+        assert_eq!(
+            Spec::parse("bots/greedy@v1.0"),
+            spec("bots/greedy", Some("v1.0"))
+        );
+        assert_eq!(Spec::parse("../bot@abc123"), spec("../bot", Some("abc123")));
+        assert_eq!(Spec::parse("bots/greedy"), spec("bots/greedy", None));
+    }
+
+    #[test]
+    fn parse_keeps_directories_containing_an_at() {
+        assert_eq!(
+            Spec::parse("/home/me@work/bot"),
+            spec("/home/me@work/bot", None)
+        );
+        assert_eq!(
+            Spec::parse("C:\\me@work\\bot"),
+            spec("C:\\me@work\\bot", None)
+        );
+        assert_eq!(
+            Spec::parse("/home/me@work/bot@v2"),
+            spec("/home/me@work/bot", Some("v2"))
+        );
+    }
+
+    #[test]
+    fn parse_ignores_an_empty_path_or_revision() {
+        assert_eq!(Spec::parse("bot@"), spec("bot@", None));
+        assert_eq!(Spec::parse("@v1"), spec("@v1", None));
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        for competitor in ["bots/greedy@v1.0", "bots/greedy"] {
+            assert_eq!(Spec::parse(competitor).to_string(), competitor);
+        }
+    }
+
+    #[test]
+    fn batch_quote_escapes_quotes_and_percent_signs() {
+        assert_eq!(batch_quote("bot.exe"), "^\"bot.exe^\"");
+        assert_eq!(batch_quote("50%"), "^\"50%%^\"");
+        assert_eq!(batch_quote("say \"hi\""), "^\"say \\^\"hi\\^\"^\"");
+        assert_eq!(batch_quote("a & b"), "^\"a ^& b^\"");
+        assert_eq!(batch_quote("C:\\bots\\"), "^\"C:\\bots\\\\^\"");
+    }
+}
@@ -1,7 +1,16 @@
+use crate::competitors::{self, Competitor, Spec};
 use crate::constants;
+use crate::game::TimeControl;
+use crate::history;
+use crate::ratings;
+use crate::seeds;
 use crate::utils;
-use log::info;
+use log::{error, info, trace, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 #[derive(Serialize, Deserialize)]
 /// `ProjectConfig` is a struct that holds the configuration for a project
@@ -31,11 +40,13 @@ pub struct ProjectConfig {
     #[serde(default)]
     pub interpreter: String,
 
-    /// Projects that are selected for competition and runs when
-    /// ```no_run
+    /// The aliases of the competitors that are selected for competition
+    /// and run when
+    /// ```bash
     /// stourney run
     /// ```
-    /// is executed
+    /// is executed. Older config files list project directories instead,
+    /// which are named when the config file is loaded
     #[serde(default)]
     pub selected_projects: Vec<String>,
 
@@ -46,8 +57,46 @@ pub struct ProjectConfig {
 
     /// The port to run a local storney server on
     /// defaults to 3030
-    #[serde(default)]
+    #[serde(default = "default_port")]
     pub port: u16,
+
+    /// The time each player starts a game with, in milliseconds
+    /// defaults to 10 seconds
+    #[serde(default = "default_initial_time_ms")]
+    pub initial_time_ms: u64,
+
+    /// The time added to a player's clock after each of their moves, in milliseconds
+    /// defaults to 1 second
+    #[serde(default = "default_increment_ms")]
+    pub increment_ms: u64,
+
+    /// The number of arenas to run at once when playing a batch of games
+    /// defaults to 0, which runs one arena per CPU
+    #[serde(default)]
+    pub workers: usize,
+
+    /// The number of games `run --headless`, `tournament` and `match` play
+    /// when not given on the command line, defaults to 0, which plays one
+    #[serde(default)]
+    pub games: usize,
+
+    /// Every named competitor, most recently added first
+    #[serde(default)]
+    pub competitors: Vec<Competitor>,
+
+    /// Named lineups and settings, selected with `--profile`
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// The competitor or project whose frontend the arena serves, `stourney`
+    /// for stourney's own copy, or empty for the first competitor that has one
+    #[serde(default)]
+    pub frontend: String,
+
+    /// Whether to skip checking for new versions of stourney and of the
+    /// scaffolding of recent projects when stourney starts
+    #[serde(default)]
+    pub offline: bool,
 }
 
 impl ::std::default::Default for ProjectConfig {
@@ -58,16 +107,40 @@ impl ::std::default::Default for ProjectConfig {
             interpreter: "./venv/bin/python3".into(),
             selected_projects: Vec::new(),
             recents: Vec::new(),
-            port: 3030,
+            port: default_port(),
+            initial_time_ms: default_initial_time_ms(),
+            increment_ms: default_increment_ms(),
+            workers: 0,
+            games: 0,
+            competitors: Vec::new(),
+            profiles: BTreeMap::new(),
+            frontend: String::new(),
+            offline: false,
         }
     }
 }
 
+// Settings whose zero value is either invalid or meaningful are given their
+// default when they are missing from the config file, rather than zero, so
+// that a migration never has to guess whether a zero was set on purpose
+
+fn default_port() -> u16 {
+    3030
+}
+
+fn default_initial_time_ms() -> u64 {
+    10_000
+}
+
+fn default_increment_ms() -> u64 {
+    1_000
+}
+
 /// Migrates the config file to the latest version
 /// by adding new unspecified fields with default values
 pub fn migrate_config() {
     let default = ProjectConfig::default();
-    let mut cfg = get_config();
+    let mut cfg = global_config();
     cfg.version = default.version;
     if cfg.api_key.is_empty() {
         cfg.api_key = default.api_key;
@@ -81,26 +154,529 @@ pub fn migrate_config() {
     if cfg.recents.is_empty() {
         cfg.recents = default.recents;
     }
-    if cfg.port == u16::default() {
-        cfg.port = default.port;
-    }
+    alias_recorded_competitors(&mut cfg);
     save_config(cfg);
 }
 
+/// Renames the competitors of the ratings ledger, the match history and the
+/// seed log, which were recorded by project directory before competitors had
+/// aliases, to the alias of the competitor playing that project. Projects
+/// that no longer exist keep their directory
+fn alias_recorded_competitors(cfg: &mut ProjectConfig) {
+    let mut alias = |recorded: &str| {
+        if cfg.competitors.iter().any(|c| c.alias == recorded) {
+            return None;
+        }
+        let spec = Spec::parse(recorded);
+        if !spec.path.contains(['/', '\\']) || !spec.check(false) {
+            return None;
+        }
+        Some(register(cfg, &spec.full()))
+    };
+    ratings::rename_competitors(&mut alias);
+    history::rename_competitors(&mut alias);
+    seeds::rename_competitors(&mut alias);
+}
+
 /// Initializes a new config file, creates one if it does not yet exist
 pub fn init_config() {
-    let cfg = get_config();
+    let mut cfg = global_config();
+    name_competitors(&mut cfg);
     let stored = confy::store(constants::CONF_FILE_NAME, None, cfg);
     purge_recents();
     stored.expect("[-] Failed to create config file");
 }
 
+/// Gives every selected project directory a named competitor,
+/// and selects it by its alias instead
+fn name_competitors(cfg: &mut ProjectConfig) {
+    let mut selected = Vec::new();
+    for project in cfg.selected_projects.clone() {
+        if cfg.competitors.iter().any(|c| c.alias == project) {
+            selected.push(project);
+            continue;
+        }
+        let spec = Spec::parse(&project);
+        if !spec.check(false) {
+            warn!(
+                "[-] Removing invalid competitor {} from the selection",
+                project
+            );
+            continue;
+        }
+        info!("[+] Naming selected project {}", project);
+        selected.push(register(cfg, &spec.full()));
+    }
+    cfg.selected_projects = selected;
+}
+
+/// Returns the alias of the competitor playing the given project, adding a
+/// new competitor to the config if there is none yet
+pub fn register(cfg: &mut ProjectConfig, spec: &Spec) -> String {
+    if let Some(competitor) = cfg.competitors.iter().find(|c| c.spec() == *spec) {
+        return competitor.alias.clone();
+    }
+    let alias = competitors::unique_alias(spec, &cfg.competitors);
+    cfg.competitors.insert(
+        0,
+        Competitor {
+            alias: alias.clone(),
+            path: spec.path.clone(),
+            language: None,
+            commit: spec.revision.clone(),
+            args: Vec::new(),
+            env: Default::default(),
+        },
+    );
+    alias
+}
+
+/// Adds the given competitor to the config, replacing the competitor
+/// with the same alias if there is one
+pub fn save_competitor(competitor: Competitor) {
+    let mut cfg = global_config();
+    cfg.competitors.retain(|c| c.alias != competitor.alias);
+    cfg.competitors.insert(0, competitor);
+    save_config(cfg);
+}
+
+/// The settings that `stourney config get` and `stourney config set` accept,
+/// besides the settings of each competitor, written `<alias>.<setting>`
+pub const SETTINGS: [&str; 8] = [
+    "port",
+    "initial_time_ms",
+    "increment_ms",
+    "workers",
+    "games",
+    "api_key",
+    "frontend",
+    "offline",
+];
+
+/// The settings of a competitor, where `env.<NAME>` is the environment variable `NAME`
+pub const COMPETITOR_SETTINGS: [&str; 5] = ["path", "language", "commit", "args", "env.<NAME>"];
+
+/// Returns the value of a setting, or a description of why there is none
+pub fn get_setting(cfg: &ProjectConfig, key: &str) -> Result<String, String> {
+    let value = match key {
+        "port" => cfg.port.to_string(),
+        "initial_time_ms" => cfg.initial_time_ms.to_string(),
+        "increment_ms" => cfg.increment_ms.to_string(),
+        "workers" => cfg.workers.to_string(),
+        "games" => cfg.games.to_string(),
+        "api_key" => cfg.api_key.clone(),
+        "frontend" => cfg.frontend.clone(),
+        "offline" => cfg.offline.to_string(),
+        "selected_projects" | "lineup" => cfg.selected_projects.join(" "),
+        _ => {
+            let (competitor, setting) = competitor_setting(cfg, key)?;
+            let competitor = &cfg.competitors[competitor];
+            match setting {
+                "path" => competitor.path.clone(),
+                "language" => competitor.language.clone().unwrap_or_default(),
+                "commit" => competitor.commit.clone().unwrap_or_default(),
+                "args" => format_args(&competitor.args),
+                _ => match setting.strip_prefix("env.") {
+                    Some(name) => competitor.env.get(name).cloned().unwrap_or_default(),
+                    None => return Err(unknown_setting(key)),
+                },
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Changes a setting, checking that the new value is valid.
+/// An empty value clears the optional settings of a competitor
+pub fn set_setting(cfg: &mut ProjectConfig, key: &str, value: &str) -> Result<(), String> {
+    let number = |value: &str| {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("{} must be a whole number, not {}", key, value))
+    };
+    match key {
+        "port" => {
+            cfg.port = match value.parse() {
+                Ok(port) if port > 0 => port,
+                _ => return Err(format!("{} is not a valid port", value)),
+            }
+        }
+        "initial_time_ms" => match number(value)? {
+            0 => return Err("The initial time must be greater than zero".to_string()),
+            milliseconds => cfg.initial_time_ms = milliseconds,
+        },
+        "increment_ms" => cfg.increment_ms = number(value)?,
+        "workers" => cfg.workers = number(value)? as usize,
+        "games" => cfg.games = number(value)? as usize,
+        "api_key" => cfg.api_key = value.to_string(),
+        "frontend" => {
+            cfg.frontend = match value {
+                "" | constants::MANAGED_FRONTEND => value.to_string(),
+                alias if cfg.competitors.iter().any(|c| c.alias == alias) => value.to_string(),
+                _ if utils::has_frontend(value) => utils::relative_to_full_path(value),
+                _ => {
+                    return Err(format!(
+                        "{} is neither a competitor nor a project with a frontend",
+                        value
+                    ))
+                }
+            }
+        }
+        "offline" => {
+            cfg.offline = value
+                .parse()
+                .map_err(|_| format!("{} must be true or false, not {}", key, value))?
+        }
+        "selected_projects" | "lineup" => {
+            return Err("Use `stourney config lineup` to select competitors".to_string())
+        }
+        _ => {
+            let (competitor, setting) = competitor_setting(cfg, key)?;
+            let competitor = &mut cfg.competitors[competitor];
+            let optional = |value: &str| (!value.is_empty()).then(|| value.to_string());
+            match setting {
+                "path" => {
+                    if !utils::check_project(value, true) {
+                        return Err(format!("{} is not a stourney project", value));
+                    }
+                    competitor.path = utils::relative_to_full_path(value);
+                }
+                "language" => {
+                    if !value.is_empty() && !Competitor::LANGUAGES.contains(&value) {
+                        return Err(format!(
+                            "Unknown language {}, expected one of {}",
+                            value,
+                            Competitor::LANGUAGES.join(", ")
+                        ));
+                    }
+                    competitor.language = optional(value);
+                }
+                "commit" => {
+                    let spec = Spec {
+                        path: competitor.path.clone(),
+                        revision: optional(value),
+                    };
+                    if !spec.check(true) {
+                        return Err(format!("{} cannot be played", spec));
+                    }
+                    competitor.commit = spec.revision;
+                }
+                "args" => competitor.args = parse_args(value)?,
+                _ => match setting.strip_prefix("env.") {
+                    Some(name) if !name.is_empty() => match optional(value) {
+                        Some(value) => {
+                            competitor.env.insert(name.to_string(), value);
+                        }
+                        None => {
+                            competitor.env.remove(name);
+                        }
+                    },
+                    _ => return Err(unknown_setting(key)),
+                },
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses the arguments of a competitor, either separated by whitespace or,
+/// for arguments that contain whitespace, as a JSON array of strings
+fn parse_args(value: &str) -> Result<Vec<String>, String> {
+    if !value.trim_start().starts_with('[') {
+        return Ok(value.split_whitespace().map(str::to_string).collect());
+    }
+    serde_json::from_str(value).map_err(|_| format!("{} is not a JSON array of strings", value))
+}
+
+/// Writes the arguments of a competitor the way `parse_args` reads them
+fn format_args(args: &[String]) -> String {
+    let plain = |arg: &String| {
+        !arg.is_empty() && !arg.starts_with('[') && !arg.contains(char::is_whitespace)
+    };
+    if args.iter().all(plain) {
+        args.join(" ")
+    } else {
+        serde_json::to_string(args).expect("[-] Failed to serialize")
+    }
+}
+
+/// Splits a competitor setting into the index of the competitor and the setting
+fn competitor_setting<'a>(cfg: &ProjectConfig, key: &'a str) -> Result<(usize, &'a str), String> {
+    let Some((alias, setting)) = key.split_once('.') else {
+        return Err(unknown_setting(key));
+    };
+    match cfg.competitors.iter().position(|c| c.alias == alias) {
+        Some(competitor) => Ok((competitor, setting)),
+        None => Err(format!("No competitor named {}", alias)),
+    }
+}
+
+fn unknown_setting(key: &str) -> String {
+    format!(
+        "Unknown setting {}, expected one of {} or <alias>.{}",
+        key,
+        SETTINGS.join(", "),
+        COMPETITOR_SETTINGS.join(", <alias>.")
+    )
+}
+
+/// Gets the config file from the specified directory, with the workspace
+/// config file and then the selected profile layered over it if there are
+/// any, or returns the default config file if it does not exist yet
+///
+/// Use `global_config` instead to change and save the config file
+pub fn get_config() -> ProjectConfig {
+    let mut cfg = global_config();
+    let workspace = current_workspace();
+    if let Some(workspace) = workspace {
+        workspace.apply(&mut cfg);
+    }
+    // A profile of the workspace takes precedence over one of the same name
+    // in the global config file
+    let Some(name) = selected_profile() else {
+        return cfg;
+    };
+    match workspace.and_then(|workspace| Some((workspace, workspace.profiles.get(name)?))) {
+        Some((workspace, profile)) => profile.apply(&mut cfg, |path| workspace.full_path(path)),
+        None => {
+            if let Some(profile) = cfg.profiles.get(name).cloned() {
+                profile.apply(&mut cfg, |path| path.to_string());
+            }
+        }
+    }
+    cfg
+}
+
 /// Gets the config file from the specified directory
 /// or returns the default config file if it does not exist yet
-pub fn get_config() -> ProjectConfig {
+pub fn global_config() -> ProjectConfig {
     let cfg = confy::load(constants::CONF_FILE_NAME, None);
-    let cfg = cfg.expect("[-] Failed to load config file");
-    cfg
+    cfg.expect("[-] Failed to load config file")
+}
+
+/// A named set of settings, such as a lineup and a time control, that
+/// takes precedence over the rest of the config when it is selected with
+/// `--profile`. Settings it leaves out keep their configured value
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The competitors to play, by alias or project path
+    #[serde(
+        default,
+        alias = "selected_projects",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub lineup: Option<Vec<String>>,
+    /// The name of a preset time control
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_time_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub increment_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub games: Option<usize>,
+}
+
+impl Profile {
+    /// Layers these settings over the given config, with project paths
+    /// in the lineup made full by `full_path`
+    fn apply(&self, cfg: &mut ProjectConfig, full_path: impl Fn(&str) -> String) {
+        if let Some(lineup) = &self.lineup {
+            cfg.selected_projects = lineup
+                .iter()
+                .map(|name| {
+                    if cfg.competitors.iter().any(|c| c.alias == *name) {
+                        return name.clone();
+                    }
+                    let spec = Spec::parse(name);
+                    Spec {
+                        path: full_path(&spec.path),
+                        revision: spec.revision,
+                    }
+                    .to_string()
+                })
+                .collect();
+        }
+        // Presets are applied first, so that explicit times take precedence
+        if let Some(preset) = self.time_control.as_deref().and_then(TimeControl::preset) {
+            cfg.initial_time_ms = preset.initial_time.as_millis() as u64;
+            cfg.increment_ms = preset.increment.as_millis() as u64;
+        }
+        cfg.initial_time_ms = self.initial_time_ms.unwrap_or(cfg.initial_time_ms);
+        cfg.increment_ms = self.increment_ms.unwrap_or(cfg.increment_ms);
+        cfg.port = self.port.unwrap_or(cfg.port);
+        cfg.workers = self.workers.unwrap_or(cfg.workers);
+        cfg.games = self.games.unwrap_or(cfg.games);
+    }
+
+    /// Returns a description of the first invalid setting, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.time_control {
+            if TimeControl::preset(name).is_none() {
+                return Err(format!(
+                    "Unknown time control {}, expected one of {}",
+                    name,
+                    TimeControl::PRESETS.join(", ")
+                ));
+            }
+        }
+        if let Some(lineup) = &self.lineup {
+            if !(2..=4).contains(&lineup.len()) {
+                return Err("A lineup needs between 2 and 4 competitors".to_string());
+            }
+        }
+        if self.initial_time_ms == Some(0) {
+            return Err("The initial time must be greater than zero".to_string());
+        }
+        if self.port == Some(0) {
+            return Err("0 is not a valid port".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Selects the profile whose settings `get_config` layers over the rest of
+/// the config for the rest of this run. Returns whether the profile exists
+pub fn select_profile(name: &str) -> bool {
+    let exists = current_workspace().is_some_and(|workspace| workspace.profiles.contains_key(name))
+        || global_config().profiles.contains_key(name);
+    if !exists {
+        error!("[-] No profile named {}", name);
+        error!("[-] Try `stourney config profiles` to list the profiles");
+        return false;
+    }
+    if SELECTED_PROFILE.set(name.to_string()).is_err() {
+        warn!("[-] A profile was already selected");
+    }
+    true
+}
+
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Returns the name of the profile selected for this run, if any
+pub fn selected_profile() -> Option<&'static str> {
+    SELECTED_PROFILE.get().map(String::as_str)
+}
+
+/// A `stourney.toml` file checked into a workspace, whose settings take
+/// precedence over the global config file. Project paths in it are
+/// relative to the directory it is in
+#[derive(Debug, Default)]
+pub struct Workspace {
+    /// The path of the workspace config file
+    pub path: PathBuf,
+    /// The settings of the workspace
+    pub settings: Profile,
+    /// Named competitors in addition to those of the global config file
+    pub competitors: Vec<Competitor>,
+    /// Profiles in addition to those of the global config file
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Workspace {
+    /// Layers the settings of this workspace over the given config
+    fn apply(&self, cfg: &mut ProjectConfig) {
+        for competitor in self.competitors.iter().rev() {
+            let mut competitor = competitor.clone();
+            competitor.path = self.full_path(&competitor.path);
+            cfg.competitors.retain(|c| c.alias != competitor.alias);
+            cfg.competitors.insert(0, competitor);
+        }
+        self.settings.apply(cfg, |path| self.full_path(path));
+    }
+
+    /// Returns the full path of a project path given in this workspace
+    fn full_path(&self, path: &str) -> String {
+        let directory = self.path.parent().unwrap_or(Path::new("."));
+        let path = directory.join(path);
+        match path.exists() {
+            true => utils::relative_to_full_path(&path.to_string_lossy()),
+            false => path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Reads and checks the workspace config file at `path`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut table: toml::Table = toml::from_str(&contents).map_err(|e| e.to_string())?;
+        // Everything but the competitors and profiles is a setting, which
+        // keeps unknown settings an error
+        let competitors: Vec<Competitor> = match table.remove("competitors") {
+            Some(competitors) => competitors.try_into().map_err(|e| e.to_string())?,
+            None => Vec::new(),
+        };
+        let profiles: BTreeMap<String, Profile> = match table.remove("profiles") {
+            Some(profiles) => profiles.try_into().map_err(|e| e.to_string())?,
+            None => BTreeMap::new(),
+        };
+        let settings: Profile = table.try_into().map_err(|e| e.to_string())?;
+
+        settings.validate()?;
+        for (name, profile) in &profiles {
+            profile
+                .validate()
+                .map_err(|e| format!("In profile {}: {}", name, e))?;
+        }
+        for competitor in &competitors {
+            if !competitors::valid_alias(&competitor.alias) {
+                return Err(format!("Invalid alias {}", competitor.alias));
+            }
+        }
+        Ok(Workspace {
+            path: path.to_path_buf(),
+            settings,
+            competitors,
+            profiles,
+        })
+    }
+}
+
+/// Returns the workspace config file in the current directory or the closest
+/// of its parents, read once per run. An invalid file is an error, as playing
+/// with only part of a shared setup would be misleading
+pub fn workspace() -> Result<Option<&'static Workspace>, String> {
+    static WORKSPACE: OnceLock<Result<Option<Workspace>, String>> = OnceLock::new();
+    let workspace = WORKSPACE.get_or_init(|| {
+        let Some(path) = workspace_file() else {
+            return Ok(None);
+        };
+        trace!("Found workspace config file {}", path.display());
+        Workspace::load(&path)
+            .map(Some)
+            .map_err(|e| format!("Invalid workspace config file {}: {}", path.display(), e))
+    });
+    workspace.as_ref().map(Option::as_ref).map_err(Clone::clone)
+}
+
+/// Returns the workspace whose settings apply. The binary checks
+/// `workspace()` at startup, so an invalid file never gets this far
+pub(crate) fn current_workspace() -> Option<&'static Workspace> {
+    workspace().ok().flatten()
+}
+
+/// Returns the path of the workspace config file in the current directory
+/// or the closest of its parents, if there is one
+pub fn workspace_file() -> Option<PathBuf> {
+    let current = std::env::current_dir().ok()?;
+    current
+        .ancestors()
+        .map(|directory| directory.join(constants::WORKSPACE_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Returns the directory the config file is stored in, where
+/// stourney also keeps the rest of its data
+pub fn config_directory() -> PathBuf {
+    let path = confy::get_configuration_file_path(constants::CONF_FILE_NAME, None);
+    let path = path.expect("[-] Failed to find config file");
+    path.parent()
+        .expect("[-] Failed to find config directory")
+        .to_path_buf()
 }
 
 /// Saves the config file
@@ -117,7 +693,7 @@ pub fn correct_version() -> bool {
 
 /// Purges the invalid directories from the recents list in the config file
 pub fn purge_recents() {
-    let mut cfg = get_config();
+    let mut cfg = global_config();
     let mut recents = cfg.recents.clone();
     recents.retain(|x| utils::check_project(x, false));
     cfg.recents = recents;
@@ -127,7 +703,7 @@ pub fn purge_recents() {
 /// Adds a valid directory to the recents list in the config file
 pub fn add_to_recents(directory: &str) {
     let directory = utils::relative_to_full_path(directory);
-    let mut cfg = get_config();
+    let mut cfg = global_config();
     if cfg.recents.contains(&directory.to_owned()) {
         let index = cfg.recents.iter().position(|x| *x == directory).unwrap();
         cfg.recents.remove(index);
@@ -147,8 +723,56 @@ pub fn display_competitors() {
         println!("No competitors selected yet!");
         println!("try running \n\tstourney config edit\nto add some competitors.");
     }
-    for competitor in cfg.selected_projects {
-        println!("  - {}", competitor);
+    for alias in &cfg.selected_projects {
+        match cfg.competitors.iter().find(|c| c.alias == *alias) {
+            Some(competitor) => display_competitor(competitor),
+            // Projects can be selected by path without naming them
+            None if Path::new(&Spec::parse(alias).path).exists() => println!("  - {}", alias),
+            None => println!("  - {} (not found)", alias),
+        }
+    }
+    let others: Vec<&Competitor> = cfg
+        .competitors
+        .iter()
+        .filter(|c| !cfg.selected_projects.contains(&c.alias))
+        .collect();
+    if !others.is_empty() {
+        println!("[+] Other competitors:");
+        for competitor in others {
+            display_competitor(competitor);
+        }
+    }
+}
+
+fn display_competitor(competitor: &Competitor) {
+    println!("  - {}: {}", competitor.alias, competitor.spec());
+    if let Some(language) = &competitor.language {
+        println!("      language: {}", language);
+    }
+    if !competitor.args.is_empty() {
+        println!("      args: {}", competitor.args.join(" "));
+    }
+    for (key, value) in &competitor.env {
+        println!("      env: {}={}", key, value);
+    }
+}
+
+pub fn display_arena_settings() {
+    let cfg = get_config();
+    let time_control = TimeControl::from_millis(cfg.initial_time_ms, cfg.increment_ms);
+    println!("[+] Time control: {}", time_control);
+    println!("[+] Port: {}", cfg.port);
+    if cfg.workers == 0 {
+        println!("[+] Workers: one per CPU");
+    } else {
+        println!("[+] Workers: {}", cfg.workers);
+    }
+    println!("[+] Games: {}", cfg.games.max(1));
+    if let Some(workspace) = current_workspace() {
+        println!("[+] Workspace config: {}", workspace.path.display());
+    }
+    if let Some(profile) = selected_profile() {
+        println!("[+] Profile: {}", profile);
     }
 }
 
@@ -162,3 +786,39 @@ pub fn check_migration() {
         migrate_config();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args_are_split_on_whitespace() {
+        assert_eq!(parse_args(" --depth  3 ").unwrap(), vec!["--depth", "3"]);
+        assert!(parse_args("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn args_containing_whitespace_are_a_json_array() {
+        let args = parse_args(r#"["--name", "Deep Blue"]"#).unwrap();
+        assert_eq!(args, vec!["--name", "Deep Blue"]);
+        assert!(parse_args("[1, 2]").is_err());
+        assert!(parse_args("[--depth").is_err());
+    }
+
+    #[test]
+    fn formatted_args_parse_back() {
+        for args in [
+            vec!["--depth", "3"],
+            vec!["--name", "Deep Blue"],
+            vec!["", "[x]"],
+            vec![],
+        ] {
+            let args: Vec<String> = args.into_iter().map(str::to_string).collect();
+            assert_eq!(parse_args(&format_args(&args)).unwrap(), args);
+        }
+        assert_eq!(
+            format_args(&["--depth".to_string(), "3".to_string()]),
+            "--depth 3"
+        );
+    }
+}
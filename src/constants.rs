@@ -1,8 +1,55 @@
 pub const CONF_FILE_NAME: &str = "stourney.conf";
+
+/// The name of the workspace config file, found in the current directory
+/// or one of its parents, which is layered over the global config file
+pub const WORKSPACE_FILE_NAME: &str = "stourney.toml";
+/// The name of the manifest that declares how to build and run a project
+/// written in any language
+pub const MANIFEST_FILE_NAME: &str = "stourney.project.toml";
 /// The version of the config file format
 /// be sure to match this with the Cargo.toml version if the
 /// config file format changes
-pub const VERSION: &str = "0.1.10";
+pub const VERSION: &str = "0.1.14";
 
 pub const STOURNEY_ARENA_REPO_URL: &str = "https://github.com/pashneal/stourney_tournament";
 
+/// The name of the file, next to the config file, that stores competitor ratings
+pub const RATINGS_FILE_NAME: &str = "ratings";
+
+/// The name of the file, next to the config file, that logs the seed of every game
+pub const SEEDS_FILE_NAME: &str = "seeds.jsonl";
+
+/// The environment variable that tells competitors the seed of the current game
+pub const SEED_ENV_VAR: &str = "STOURNEY_SEED";
+
+/// The name of the directory, next to the config file, that holds the results of headless runs
+pub const RESULTS_DIRECTORY: &str = "results";
+
+/// The name of the directory, next to the config file, that holds the replay of every game
+pub const REPLAYS_DIRECTORY: &str = "replays";
+
+/// The name of the match history database, next to the config file
+pub const HISTORY_FILE_NAME: &str = "history.sqlite";
+
+/// The name of the directory, next to the config file, that holds the worktrees
+/// of competitors pinned to a git revision
+pub const WORKTREES_DIRECTORY: &str = "worktrees";
+
+/// The name of the directory, next to the config file, that holds the scripts
+/// launching competitors with extra arguments or environment variables
+pub const LAUNCHERS_DIRECTORY: &str = "launchers";
+
+/// The name of the build manifest, kept in every project directory, which records
+/// what the last successful builds of the project were built from
+pub const BUILD_MANIFEST_FILE_NAME: &str = ".stourney-build.json";
+
+/// The name of the directory, next to the config file, that holds the copy of
+/// the scaffolding whose frontend is served when no competitor has one
+pub const SCAFFOLDING_DIRECTORY: &str = "scaffolding";
+
+/// The frontend setting that serves the frontend of stourney's own copy of the scaffolding
+pub const MANAGED_FRONTEND: &str = "stourney";
+
+/// The name of the file, next to the config file, that caches the results of
+/// the checks for updates made when stourney starts
+pub const UPDATE_CHECK_FILE_NAME: &str = "update-check.json";
@@ -1,5 +1,5 @@
+use crate::competitors::{self, Competitor, Spec};
 use crate::config;
-use crate::utils;
 use dialoguer::{theme::ColorfulTheme, Select};
 use log::error;
 use std::path::{Path, PathBuf};
@@ -14,7 +14,7 @@ pub fn confirm_delete() -> bool {
         .interact()
         .expect("[-] Failed to get delete confirmation");
 
-    return selection == 0;
+    selection == 0
 }
 
 pub fn language() -> &'static str {
@@ -27,7 +27,7 @@ pub fn language() -> &'static str {
         .interact()
         .expect("[-] Failed to get language selection");
 
-    return selections[selection];
+    selections[selection]
 }
 
 pub fn rust_template() -> PathBuf {
@@ -54,7 +54,7 @@ pub fn rust_template() -> PathBuf {
         Path::new("examples").join("rust").join("simple"),
     ];
 
-    return paths[selection].clone();
+    paths[selection].clone()
 }
 
 pub fn python_template() -> PathBuf {
@@ -83,7 +83,7 @@ pub fn python_template() -> PathBuf {
         Path::new("examples").join("python").join("timeout"),
     ];
 
-    return paths[selection].clone();
+    paths[selection].clone()
 }
 
 pub fn num_competitors() -> usize {
@@ -99,20 +99,32 @@ pub fn num_competitors() -> usize {
     selection + 2
 }
 
-/// Returns a directory pointing to a project created by the
-/// `stourney new` command, or a manually entered project directory
+/// Returns the alias of a named competitor, or of a new competitor
+/// playing a recent project or a manually entered project directory
+///
+/// Only reads the config file, which the answers are saved to, so that
+/// workspace and profile settings are not copied into it
 ///
 /// Returns `None` if the directory is invalid
 pub fn select_recent_project(competitor_num: usize) -> Option<String> {
-    let config = config::get_config();
-    let selections = config.recents.clone();
-    let mut selections = selections.iter().take(9).cloned().collect::<Vec<String>>();
+    let config = config::global_config();
+    let competitors = config.competitors.iter().take(9).collect::<Vec<_>>();
+    let recents = config
+        .recents
+        .iter()
+        .filter(|path| !config.competitors.iter().any(|c| c.path == **path))
+        .take(9)
+        .collect::<Vec<_>>();
+    let mut selections = competitors
+        .iter()
+        .map(|competitor| competitor.to_string())
+        .chain(recents.iter().map(|path| path.to_string()))
+        .collect::<Vec<String>>();
     selections.push("Other...".to_owned());
-    // TODO: convert recents to relative dir
 
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt(format!(
-            "[Competitor {}] Select a recent project",
+            "[Competitor {}] Select a competitor or a recent project",
             competitor_num
         ))
         .default(0)
@@ -120,21 +132,53 @@ pub fn select_recent_project(competitor_num: usize) -> Option<String> {
         .interact()
         .expect("[-] Failed to get recent project selection");
 
-    let directory = if selection == config.recents.len() {
+    if selection < competitors.len() {
+        return Some(competitors[selection].alias.clone());
+    }
+    let directory = if selection == selections.len() - 1 {
         dialoguer::Input::<String>::new()
-            .with_prompt("Enter the path to the project directory")
+            .with_prompt(
+                "Enter the path to the project directory, optionally followed by @commit or @tag",
+            )
             .interact()
             .expect("[-] Failed to get new project directory")
     } else {
-        selections[selection].clone()
+        recents[selection - competitors.len()].clone()
     };
 
-    if utils::check_project(&directory, true) {
-        let full_path = utils::relative_to_full_path(&directory);
-        config::add_to_recents(&full_path);
-        Some(full_path)
-    } else {
+    let spec = Spec::parse(&directory);
+    if !spec.check(true) {
         error!("[-] Invalid project directory");
-        None
+        return None;
     }
+    let spec = spec.full();
+    config::add_to_recents(&spec.path);
+    let config = config::global_config();
+    if let Some(competitor) = config.competitors.iter().find(|c| c.spec() == spec) {
+        return Some(competitor.alias.clone());
+    }
+
+    let alias = dialoguer::Input::<String>::new()
+        .with_prompt("Enter a name for the competitor")
+        .default(competitors::unique_alias(&spec, &config.competitors))
+        .validate_with(|alias: &String| {
+            if !competitors::valid_alias(alias) {
+                Err("Use only letters, digits, '-', '_', '.' and '@'")
+            } else if config.competitors.iter().any(|c| c.alias == *alias) {
+                Err("Another competitor already has this name")
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()
+        .expect("[-] Failed to get competitor name");
+    config::save_competitor(Competitor {
+        alias: alias.clone(),
+        path: spec.path,
+        language: None,
+        commit: spec.revision,
+        args: Vec::new(),
+        env: Default::default(),
+    });
+    Some(alias)
 }
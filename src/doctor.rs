@@ -0,0 +1,504 @@
+//! Contains the checks of `stourney doctor`, which diagnose everything
+//! stourney needs to build and play competitors: the tools it runs, the
+//! virtual environment and scaffolding of every project, the port and the
+//! config files. Every failed check suggests a fix
+use crate::competitors;
+use crate::config::{self, ProjectConfig, Workspace};
+use crate::constants;
+use crate::utils;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How a check went
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    /// Nothing is broken yet, but something should be looked at
+    Warn,
+    Fail,
+}
+
+/// The result of a single check
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// What was checked
+    pub name: String,
+    pub status: Status,
+    /// What was found, such as a version or an error
+    pub detail: String,
+    /// What to do about a check that did not pass
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// A titled group of checks
+pub struct Section {
+    pub title: String,
+    pub checks: Vec<Check>,
+}
+
+/// Runs every check, grouped into sections
+pub fn diagnose() -> Vec<Section> {
+    let mut sections = vec![
+        Section {
+            title: "Prerequisites".to_string(),
+            checks: prerequisite_checks(),
+        },
+        Section {
+            title: "Toolchain".to_string(),
+            checks: toolchain_checks(),
+        },
+    ];
+    let (config_checks, cfg) = config_checks();
+    sections.push(Section {
+        title: "Config".to_string(),
+        checks: config_checks,
+    });
+    // Without a valid config there are no projects or port to check
+    let Some(cfg) = cfg else {
+        return sections;
+    };
+    sections.push(Section {
+        title: "Port".to_string(),
+        checks: vec![port_check(cfg.port)],
+    });
+    for project in projects(&cfg) {
+        sections.push(Section {
+            title: format!("Project {}", project),
+            checks: project_checks(&project),
+        });
+    }
+    sections
+}
+
+/// Checks for git, python 3 and the pip and venv modules of python,
+/// which are needed to create a project
+pub fn prerequisite_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    checks.push(match output("git", &["--version"]) {
+        Some(version) => Check::pass("git", version),
+        None => Check::fail(
+            "git",
+            "not found",
+            "Install git from https://git-scm.com/downloads",
+        ),
+    });
+
+    let Some(python) = utils::python3_found() else {
+        checks.push(Check::fail(
+            "python",
+            "python 3 not found",
+            "Install python 3.9 or newer from https://www.python.org/downloads",
+        ));
+        return checks;
+    };
+    // The windows launcher `py` needs to be told to use python 3
+    let base: &[&str] = if python == "py" { &["-3"] } else { &[] };
+    let args = |extra: &[&'static str]| -> Vec<&'static str> {
+        base.iter().chain(extra).copied().collect()
+    };
+    checks.push(match output(&python, &args(&["--version"])) {
+        Some(version) if minor_version(&version, "Python 3.").is_some_and(|minor| minor >= 9) => {
+            Check::pass("python", version)
+        }
+        Some(version) => Check::fail(
+            "python",
+            format!("{} is too old", version),
+            "Install python 3.9 or newer from https://www.python.org/downloads",
+        ),
+        None => Check::fail(
+            "python",
+            format!("`{} --version` failed", python),
+            "Reinstall python 3.9 or newer from https://www.python.org/downloads",
+        ),
+    });
+    checks.push(match output(&python, &args(&["-m", "pip", "--version"])) {
+        Some(version) => Check::pass("pip", version),
+        None => Check::fail(
+            "pip",
+            format!("no pip module for {}", python),
+            format!(
+                "Run `{} -m ensurepip --upgrade`, or install your system's python3-pip package",
+                python
+            ),
+        ),
+    });
+    checks.push(match output(&python, &args(&["-m", "venv", "--help"])) {
+        Some(_) => Check::pass("venv", "found"),
+        None => Check::fail(
+            "venv",
+            format!("no venv module for {}", python),
+            "Install your system's python3-venv package",
+        ),
+    });
+    checks
+}
+
+/// Checks the versions of cargo and rustc, which build rust competitors
+/// and the python bindings of the scaffolding
+fn toolchain_checks() -> Vec<Check> {
+    let fix = "Install rust with rustup from https://rustup.rs, or run `rustup update`";
+    ["cargo", "rustc"]
+        .iter()
+        .map(|tool| match output(tool, &["--version"]) {
+            Some(version)
+                if minor_version(&version, &format!("{} 1.", tool))
+                    .is_some_and(|minor| minor >= 50) =>
+            {
+                Check::pass(tool, version)
+            }
+            Some(version) => Check::fail(tool, format!("{} is older than 1.50", version), fix),
+            None => Check::fail(tool, "not found", fix),
+        })
+        .collect()
+}
+
+/// Checks that the global config file, the workspace config file and every
+/// competitor, lineup and profile in them are valid, and returns the
+/// resulting config if it could be read
+fn config_checks() -> (Vec<Check>, Option<ProjectConfig>) {
+    let mut checks = Vec::new();
+    let path = confy::get_configuration_file_path(constants::CONF_FILE_NAME, None)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| constants::CONF_FILE_NAME.to_string());
+    match confy::load::<ProjectConfig>(constants::CONF_FILE_NAME, None) {
+        Ok(_) => checks.push(Check::pass("config file", &path)),
+        Err(e) => {
+            checks.push(Check::fail(
+                "config file",
+                format!("{}: {}", path, e),
+                format!(
+                    "Fix the file, or delete it to start over with the defaults: {}",
+                    path
+                ),
+            ));
+            return (checks, None);
+        }
+    }
+    if let Some(path) = config::workspace_file() {
+        match Workspace::load(&path) {
+            Ok(_) => checks.push(Check::pass("workspace config", path.display().to_string())),
+            Err(e) => {
+                checks.push(Check::fail(
+                    "workspace config",
+                    format!("{}: {}", path.display(), e),
+                    format!("Fix or remove the invalid setting in {}", path.display()),
+                ));
+                return (checks, None);
+            }
+        }
+    }
+
+    let cfg = config::get_config();
+    for competitor in &cfg.competitors {
+        let name = format!("competitor {}", competitor.alias);
+        let spec = competitor.spec();
+        if spec.check(false) {
+            checks.push(Check::pass(&name, spec.to_string()));
+        } else {
+            checks.push(Check::fail(
+                &name,
+                format!("{} cannot be played", spec),
+                format!(
+                    "Fix its path or commit with `stourney config set {}.path` or `{}.commit`, \
+                     or remove it with `stourney config remove {}`",
+                    competitor.alias, competitor.alias, competitor.alias
+                ),
+            ));
+        }
+    }
+    let lineup = &cfg.selected_projects;
+    let unknown: Vec<&String> = lineup
+        .iter()
+        .filter(|name| {
+            !cfg.competitors.iter().any(|c| &c.alias == *name)
+                && !competitors::Spec::parse(name).check(false)
+        })
+        .collect();
+    if !(2..=4).contains(&lineup.len()) {
+        checks.push(Check::warn(
+            "lineup",
+            format!("{} competitors selected", lineup.len()),
+            "Select 2 to 4 competitors with `stourney config lineup <competitors>`",
+        ));
+    } else if !unknown.is_empty() {
+        let unknown: Vec<&str> = unknown.iter().map(|name| name.as_str()).collect();
+        checks.push(Check::fail(
+            "lineup",
+            format!("{} cannot be played", unknown.join(", ")),
+            "Select other competitors with `stourney config lineup <competitors>`",
+        ));
+    } else {
+        checks.push(Check::pass("lineup", lineup.join(" ")));
+    }
+    for (name, profile) in &cfg.profiles {
+        let check = format!("profile {}", name);
+        match profile.validate() {
+            Ok(()) => checks.push(Check::pass(&check, "valid")),
+            Err(e) => checks.push(Check::fail(
+                &check,
+                e,
+                format!(
+                    "Save it again with `stourney config profiles save {}`, \
+                     or delete it with `stourney config profiles delete {}`",
+                    name, name
+                ),
+            )),
+        }
+    }
+    (checks, Some(cfg))
+}
+
+/// Checks that the configured port is free for the arena
+fn port_check(port: u16) -> Check {
+    let name = format!("port {}", port);
+    if utils::port_available(port) {
+        return Check::pass(&name, "available");
+    }
+    let detail = match utils::port_owner(port) {
+        Some(owner) => format!("taken by {}", owner),
+        None => "taken".to_string(),
+    };
+    let fix = match utils::next_available_port(port) {
+        Some(free) => format!(
+            "Stop whatever is using it, or run `stourney config set port {}`",
+            free
+        ),
+        None => "Stop whatever is using it".to_string(),
+    };
+    // Stourney moves on to the next free port by itself
+    Check::warn(&name, detail, fix)
+}
+
+/// Returns the directory of every project in the config file, registered
+/// or recently played, that still exists
+fn projects(cfg: &ProjectConfig) -> BTreeSet<String> {
+    cfg.competitors
+        .iter()
+        .map(|competitor| competitor.path.clone())
+        .chain(cfg.recents.iter().cloned())
+        .filter(|path| Path::new(path).is_dir())
+        .collect()
+}
+
+/// Checks that a project is a valid project and, for projects created with
+/// `stourney new`, that its virtual environment can build and import the
+/// python bindings of its scaffolding, and that its scaffolding is up to date
+fn project_checks(project: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let project_type = utils::guess_project_type(project);
+    if !utils::check_project(project, false) {
+        checks.push(Check::fail(
+            "project",
+            "not a stourney project",
+            format!(
+                "Run `stourney config show` for details, or create a new project with \
+                 `stourney new` and move its sources over: {}",
+                project
+            ),
+        ));
+        return checks;
+    }
+    checks.push(Check::pass(
+        "project",
+        format!("{:?}", project_type).to_lowercase(),
+    ));
+    if project_type == utils::ProjectType::Manifest {
+        return checks;
+    }
+
+    // Rust competitors are played without their virtual environment
+    checks.extend(venv_checks(project).into_iter().map(|mut check| {
+        if project_type == utils::ProjectType::Rust && check.status == Status::Fail {
+            check.status = Status::Warn;
+        }
+        check
+    }));
+    checks.push(scaffolding_check(project));
+    checks
+}
+
+/// Returns the directory of the binaries of a project's virtual environment
+fn venv_binaries(project: &str) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        Path::new(project).join("venv").join("Scripts")
+    } else {
+        Path::new(project).join("venv").join("bin")
+    }
+}
+
+fn venv_checks(project: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let interpreter = utils::python_interpreter_path(project);
+    let binaries = venv_binaries(project);
+    let pip = binaries.join("pip");
+    let recreate = format!(
+        "Recreate it with `python3 -m venv {}` then `{} install maturin[patchelf]`",
+        Path::new(project).join("venv").display(),
+        pip.display()
+    );
+    match output(&interpreter, &["--version"]) {
+        Some(version) => checks.push(Check::pass("venv", version)),
+        None => {
+            checks.push(Check::fail("venv", "its python does not run", recreate));
+            return checks;
+        }
+    }
+
+    let install = format!("Run `{} install maturin[patchelf]`", pip.display());
+    checks.push(
+        match output(&interpreter, &["-m", "maturin", "--version"]) {
+            Some(version) => Check::pass("maturin", version),
+            None => Check::fail("maturin", "not installed in the venv", &install),
+        },
+    );
+    // Maturin needs patchelf to build the bindings on linux
+    if cfg!(target_os = "linux") {
+        let patchelf = binaries.join("patchelf");
+        checks.push(match output(&patchelf.to_string_lossy(), &["--version"]) {
+            Some(version) => Check::pass("patchelf", version),
+            None => Check::fail("patchelf", "not installed in the venv", &install),
+        });
+    }
+
+    let Some(module) = ffi_module(project) else {
+        checks.push(Check::warn(
+            "python bindings",
+            "the scaffolding has no python_ffi crate",
+            "Run `stourney update` to update the scaffolding",
+        ));
+        return checks;
+    };
+    let import = format!("import {}", module);
+    checks.push(match output(&interpreter, &["-c", &import]) {
+        Some(_) => Check::pass("python bindings", format!("`{}` works", import)),
+        None => Check::fail(
+            "python bindings",
+            format!("`{}` failed", import),
+            format!(
+                "Rebuild them: `cd {} && maturin build --release -m {} --out . && \
+                 pip install --force-reinstall *.whl`, with the venv activated",
+                binaries.display(),
+                ffi_manifest(project).display()
+            ),
+        ),
+    });
+    checks
+}
+
+/// Returns the Cargo.toml of the python bindings of a project's scaffolding
+fn ffi_manifest(project: &str) -> PathBuf {
+    Path::new(project)
+        .join("lib")
+        .join("scaffolding")
+        .join("python_ffi")
+        .join("Cargo.toml")
+}
+
+/// Returns the name of the python module of a project's python bindings,
+/// the name of the library of their crate
+fn ffi_module(project: &str) -> Option<String> {
+    let manifest = std::fs::read_to_string(ffi_manifest(project)).ok()?;
+    let manifest: toml::Table = toml::from_str(&manifest).ok()?;
+    let name = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .or_else(|| manifest.get("package")?.get("name"))?
+        .as_str()?;
+    Some(name.replace('-', "_"))
+}
+
+/// Reports the commit of a project's scaffolding and whether it is behind
+fn scaffolding_check(project: &str) -> Check {
+    let scaffolding = Path::new(project).join("lib").join("scaffolding");
+    let current = match scaffolding.is_dir() {
+        true => utils::current_scaffolding_version(project),
+        false => String::new(),
+    };
+    let short = |commit: &str| commit.chars().take(12).collect::<String>();
+    if current.is_empty() {
+        return Check::fail(
+            "scaffolding",
+            "lib/scaffolding is not a git repository",
+            format!(
+                "Clone it again with `git clone {} {}`",
+                constants::STOURNEY_ARENA_REPO_URL,
+                Path::new(project).join("lib").display()
+            ),
+        );
+    }
+    let remote = utils::current_scaffolding_remote_version(project);
+    if !remote.is_empty() && current != remote {
+        return Check::warn(
+            "scaffolding",
+            format!(
+                "at {}, behind origin/main at {}",
+                short(&current),
+                short(&remote)
+            ),
+            "Run `stourney update`",
+        );
+    }
+    Check::pass("scaffolding", format!("at {}", short(&current)))
+}
+
+/// Runs a program and returns the first line it printed if it succeeded
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Older pythons print their version to stderr
+    let printed = match output.stdout.is_empty() {
+        true => output.stderr,
+        false => output.stdout,
+    };
+    let printed = String::from_utf8_lossy(&printed);
+    Some(
+        printed
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    )
+}
+
+/// Returns the minor version from a version string such as `Python 3.11.2`,
+/// given what comes before it, `Python 3.`
+fn minor_version(version: &str, prefix: &str) -> Option<u32> {
+    let minor = version.strip_prefix(prefix)?;
+    let end = minor
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(minor.len());
+    minor[..end].parse().ok()
+}
@@ -0,0 +1,389 @@
+//! Contains utilities for running a single game of Splendor between
+//! competitors and collecting the result once the game is over
+//!
+//! The arena can only host a single game per process, so every game is run
+//! in a child stourney process, which passes the seed on to the competitors,
+//! watches the game as it is played and writes a record of it to a file
+//! once it is over
+use crate::config;
+use crate::constants;
+use crate::ratings;
+use crate::replays::{Move, Position};
+use log::{error, info, trace};
+use serde::{Deserialize, Serialize};
+use splendor_arena::tokio;
+use splendor_arena::ArenaBuilder;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The prestige points a player needs to win the game
+pub const WINNING_POINTS: u8 = 15;
+
+/// How long to wait between checks of whether a game is over
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for a single game before giving up on it
+const GAME_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// The time each player starts a game with and the time
+/// added to their clock after each of their moves
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub initial_time: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    /// The names of the preset time controls, fastest first
+    pub const PRESETS: [&'static str; 3] = ["bullet", "blitz", "rapid"];
+
+    pub fn from_millis(initial_time: u64, increment: u64) -> Self {
+        TimeControl {
+            initial_time: Duration::from_millis(initial_time),
+            increment: Duration::from_millis(increment),
+        }
+    }
+
+    /// Returns the preset time control with the given name, if there is one
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "bullet" => Some(TimeControl::from_millis(2_000, 200)),
+            "blitz" => Some(TimeControl::from_millis(10_000, 1_000)),
+            "rapid" => Some(TimeControl::from_millis(60_000, 5_000)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}s + {}s",
+            self.initial_time.as_secs_f64(),
+            self.increment.as_secs_f64()
+        )
+    }
+}
+
+/// Everything needed to launch an arena for a single game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSetup {
+    /// The alias of each competitor, in seat order
+    pub competitors: Vec<String>,
+    /// The project each competitor plays, its directory followed by
+    /// `@revision` if it is pinned, in seat order
+    #[serde(default)]
+    pub projects: Vec<String>,
+    /// The binaries or python files to run, in seat order
+    pub binaries: Vec<String>,
+    /// The python interpreter used to run python competitors
+    pub interpreter: String,
+    /// The location of the frontend served by the arena,
+    /// or `None` to run without a frontend
+    pub static_files: Option<String>,
+    /// The port the arena is served on
+    pub port: u16,
+    /// The clock each player plays with
+    pub time_control: TimeControl,
+    /// The seed of the game, exported to every competitor
+    /// through the environment variable `STOURNEY_SEED`
+    pub seed: u64,
+    /// Whether the arena sends the game to stourney.com to be watched there
+    #[serde(default)]
+    pub web: bool,
+    /// Where the arena process writes the record of the game once it is over
+    #[serde(default)]
+    pub record: Option<PathBuf>,
+}
+
+impl GameSetup {
+    /// Returns a copy of this setup that only seats the given competitors,
+    /// given as indices into `binaries`
+    pub fn lineup(&self, seats: &[usize]) -> GameSetup {
+        GameSetup {
+            competitors: seats.iter().map(|&i| self.competitors[i].clone()).collect(),
+            projects: seats.iter().map(|&i| self.projects[i].clone()).collect(),
+            binaries: seats.iter().map(|&i| self.binaries[i].clone()).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Converts the setup into an arena ready to be built
+    pub fn arena(&self) -> ArenaBuilder {
+        let arena = ArenaBuilder::new()
+            .port(self.port)
+            .binaries(self.binaries.clone())
+            .initial_time(self.time_control.initial_time)
+            .increment(self.time_control.increment)
+            .python_interpreter(&self.interpreter)
+            .static_files(&self.static_files());
+        if self.web {
+            arena.send_to_web(true, &config::get_config().api_key)
+        } else {
+            arena
+        }
+    }
+
+    /// Returns the directory of static files for the arena to serve. Without a
+    /// frontend this is an empty directory: the arena always serves one, and
+    /// its server is still needed for the competitors to connect to
+    pub fn static_files(&self) -> String {
+        if let Some(static_files) = &self.static_files {
+            return static_files.clone();
+        }
+        let empty = std::env::temp_dir().join("stourney_headless");
+        if let Err(e) = std::fs::create_dir_all(&empty) {
+            error!("[-] Failed to create {}: {}", empty.display(), e);
+        }
+        empty.to_string_lossy().to_string()
+    }
+
+    /// Returns the file the arena process for this game writes its record
+    /// to, which is unique to this stourney process and the game's port
+    pub fn record_file(&self) -> PathBuf {
+        std::env::temp_dir().join("stourney_games").join(format!(
+            "{}-{}.json",
+            std::process::id(),
+            self.port
+        ))
+    }
+
+    /// Launches the arena in a separate stourney process, which passes the
+    /// seed on to the competitors it launches through its environment and
+    /// writes the record of the game to `record_file`
+    pub fn spawn(&self, stdout: Stdio) -> std::io::Result<Child> {
+        let record = self.record_file();
+        if let Some(directory) = record.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        // A record left behind by an earlier game on the same port
+        let _ = fs::remove_file(&record);
+        let setup = GameSetup {
+            record: Some(record),
+            ..self.clone()
+        };
+        let setup = serde_json::to_string(&setup).expect("[-] Failed to serialize game setup");
+        let executable = std::env::current_exe()?;
+        Command::new(executable)
+            .arg("arena")
+            .arg(setup)
+            .env(constants::SEED_ENV_VAR, self.seed.to_string())
+            .stdout(stdout)
+            .spawn()
+    }
+
+    /// Plays a single game in a separate stourney process and
+    /// waits for the result
+    pub fn play(&self) -> Result<GameResult, GameError> {
+        let child = self.spawn(Stdio::null()).map_err(GameError::Launch)?;
+
+        let mut child = ArenaProcess(child);
+        let result = wait_for_result(&mut child.0, &self.record_file())?;
+        trace!("Game finished with scores {:?}", result.scores);
+        Ok(result)
+    }
+}
+
+/// Kills the arena process once the result is no longer needed
+struct ArenaProcess(Child);
+
+impl Drop for ArenaProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// The outcome of a single finished game, indexed by seat
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    /// The prestige points each seat finished with
+    pub scores: Vec<u8>,
+    /// The number of development cards each seat finished with
+    pub developments: Vec<u32>,
+    /// The winning seat, or `None` if the game was drawn
+    pub winner: Option<usize>,
+    /// Every move played
+    pub moves: Vec<Move>,
+    /// The position after every move
+    pub positions: Vec<Position>,
+}
+
+impl GameResult {
+    /// Determines the winner using the Splendor rules: the most points
+    /// (at least 15), with the fewest development cards breaking ties.
+    /// A tie on both counts, or a game where nobody reached 15 points,
+    /// is a draw. The ratings place the players by the same rule
+    fn new(scores: Vec<u8>, developments: Vec<u32>, moves: Vec<Move>) -> Self {
+        let places = ratings::places(&scores, &developments);
+        let mut first = (0..scores.len()).filter(|&seat| places[seat] == 0);
+        let winner = match (first.next(), first.next()) {
+            (Some(seat), None) => Some(seat),
+            _ => None,
+        };
+        GameResult {
+            scores,
+            developments,
+            winner,
+            moves,
+            positions: Vec::new(),
+        }
+    }
+}
+
+/// The reasons a game can fail to produce a result
+#[derive(Debug)]
+pub enum GameError {
+    /// The arena process could not be started
+    Launch(std::io::Error),
+    /// The arena process exited before the game was over
+    Crashed,
+    /// The game did not finish within the allotted time
+    TimedOut,
+    /// The competitor in the given seat exited or failed to launch
+    /// before the game was over
+    Forfeited(usize),
+}
+
+impl GameError {
+    /// Returns the seat whose competitor caused the game to fail, if the
+    /// failure can be put down to one
+    pub fn seat(&self) -> Option<usize> {
+        match self {
+            GameError::Forfeited(seat) => Some(*seat),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::Launch(e) => write!(f, "failed to launch the arena: {}", e),
+            GameError::Crashed => write!(f, "the arena exited before the game was over"),
+            GameError::TimedOut => write!(f, "the game did not finish in time"),
+            GameError::Forfeited(seat) => write!(
+                f,
+                "the competitor in seat {} exited before the game was over",
+                seat + 1
+            ),
+        }
+    }
+}
+
+/// Everything the arena process records about a finished game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// The prestige points each seat finished with
+    pub scores: Vec<u8>,
+    /// The number of development cards each seat finished with
+    pub developments: Vec<u32>,
+    pub moves: Vec<Move>,
+    /// The position after every move
+    pub positions: Vec<Position>,
+    /// The seat whose competitor exited or failed to launch before the
+    /// game was over, in which case nothing else is recorded
+    #[serde(default)]
+    pub forfeit: Option<usize>,
+}
+
+impl GameRecord {
+    /// Returns the record of a game forfeited by the competitor in `seat`
+    pub fn forfeited(seat: usize) -> Self {
+        GameRecord {
+            scores: Vec::new(),
+            developments: Vec::new(),
+            moves: Vec::new(),
+            positions: Vec::new(),
+            forfeit: Some(seat),
+        }
+    }
+
+    /// Writes the record to `path`, through a file next to it that is renamed
+    /// into place so that the record is never read half written
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let contents = serde_json::to_string(self).expect("[-] Failed to serialize game record");
+        let partial = path.with_extension("partial");
+        fs::write(&partial, contents)?;
+        fs::rename(&partial, path)
+    }
+
+    /// Reads the record at `path`, if it has been written
+    fn read(path: &Path) -> Option<GameRecord> {
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                trace!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn into_result(self) -> Result<GameResult, GameError> {
+        if let Some(seat) = self.forfeit {
+            return Err(GameError::Forfeited(seat));
+        }
+        info!("[+] Game finished after {} moves", self.moves.len());
+        Ok(GameResult {
+            positions: self.positions,
+            ..GameResult::new(self.scores, self.developments, self.moves)
+        })
+    }
+}
+
+/// Waits for the arena process to write the record of its game to `record`,
+/// then reads the result from it
+fn wait_for_result(child: &mut Child, record: &Path) -> Result<GameResult, GameError> {
+    let start = Instant::now();
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if let Some(game) = GameRecord::read(record) {
+            let _ = fs::remove_file(record);
+            return game.into_result();
+        }
+        if !matches!(child.try_wait(), Ok(None)) {
+            error!("[-] Arena writing {} exited unexpectedly", record.display());
+            return Err(GameError::Crashed);
+        }
+        if start.elapsed() > GAME_TIMEOUT {
+            return Err(GameError::TimedOut);
+        }
+    }
+}
+
+/// Waits for an arena process that is left running once the game is over
+/// to write the record of its game to `record`, then reads the result from it
+pub async fn watch_for_result(record: &Path) -> Result<GameResult, GameError> {
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        if let Some(game) = GameRecord::read(record) {
+            let _ = fs::remove_file(record);
+            return game.into_result();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn winner_agrees_with_the_places() {
+        let result = GameResult::new(vec![15, 17, 9], vec![10, 12, 6], Vec::new());
+        assert_eq!(result.winner, Some(1));
+        let result = GameResult::new(vec![15, 15], vec![8, 8], Vec::new());
+        assert_eq!(result.winner, None);
+        // Nobody reached 15 points, so the leader on points did not win
+        let result = GameResult::new(vec![14, 10], vec![8, 6], Vec::new());
+        assert_eq!(result.winner, None);
+        assert_eq!(
+            ratings::places(&result.scores, &result.developments),
+            [0, 0]
+        );
+    }
+}
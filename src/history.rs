@@ -0,0 +1,236 @@
+//! Contains a SQLite database of every completed game, stored next to
+//! the config file, and the queries behind `stourney history`
+use crate::config;
+use crate::constants;
+use crate::game::TimeControl;
+use crate::results::{self, GameReport};
+use crate::utils;
+use log::{trace, warn};
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::time::Duration;
+
+/// Opens the history database, creating its tables if they do not exist yet
+fn open() -> rusqlite::Result<Connection> {
+    let path = config::config_directory().join(constants::HISTORY_FILE_NAME);
+    let connection = Connection::open(path)?;
+    // Parallel games all write to the database as they finish
+    connection.busy_timeout(Duration::from_secs(5))?;
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS games (
+            id INTEGER PRIMARY KEY,
+            finished_at INTEGER NOT NULL,
+            seed TEXT NOT NULL,
+            initial_time_ms INTEGER NOT NULL,
+            increment_ms INTEGER NOT NULL,
+            duration REAL NOT NULL,
+            moves INTEGER NOT NULL,
+            winner INTEGER,
+            failure TEXT
+        );
+        CREATE TABLE IF NOT EXISTS seats (
+            game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+            seat INTEGER NOT NULL,
+            competitor TEXT NOT NULL,
+            score INTEGER,
+            PRIMARY KEY (game_id, seat)
+        );
+        CREATE INDEX IF NOT EXISTS games_finished_at ON games(finished_at);",
+    )?;
+    Ok(connection)
+}
+
+/// Adds a completed or failed game to the history
+pub fn record(report: &GameReport) {
+    if let Err(e) = insert(report) {
+        warn!(
+            "[-] Failed to record game {} in the history: {}",
+            report.seed, e
+        );
+    }
+}
+
+fn insert(report: &GameReport) -> rusqlite::Result<()> {
+    let mut connection = open()?;
+    let transaction = connection.transaction()?;
+    transaction.execute(
+        "INSERT INTO games (finished_at, seed, initial_time_ms, increment_ms, duration, moves, winner, failure)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            report.finished_at as i64,
+            report.seed.to_string(),
+            report.time_control.initial_time.as_millis() as i64,
+            report.time_control.increment.as_millis() as i64,
+            report.duration,
+            report.moves as i64,
+            report.winner.map(|seat| seat as i64),
+            report.failure,
+        ],
+    )?;
+    let game_id = transaction.last_insert_rowid();
+    for (seat, competitor) in report.competitors.iter().enumerate() {
+        transaction.execute(
+            "INSERT INTO seats (game_id, seat, competitor, score) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                game_id,
+                seat as i64,
+                competitor,
+                report.scores.get(seat).map(|&score| score as i64),
+            ],
+        )?;
+    }
+    transaction.commit()
+}
+
+/// Renames every competitor in the history that `alias` gives a new name
+pub fn rename_competitors(alias: &mut dyn FnMut(&str) -> Option<String>) {
+    // Nothing to rename, and no database to create, before the first game
+    if !config::config_directory()
+        .join(constants::HISTORY_FILE_NAME)
+        .exists()
+    {
+        return;
+    }
+    if let Err(e) = rename(alias) {
+        warn!("[-] Failed to rename competitors in the history: {}", e);
+    }
+}
+
+fn rename(alias: &mut dyn FnMut(&str) -> Option<String>) -> rusqlite::Result<()> {
+    let mut connection = open()?;
+    let transaction = connection.transaction()?;
+    let competitors = transaction
+        .prepare("SELECT DISTINCT competitor FROM seats")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for competitor in competitors {
+        if let Some(name) = alias(&competitor) {
+            trace!("Renaming {} to {} in the history", competitor, name);
+            transaction.execute(
+                "UPDATE seats SET competitor = ?1 WHERE competitor = ?2",
+                params![name, competitor],
+            )?;
+        }
+    }
+    transaction.commit()
+}
+
+/// Returns every game that finished at or after the given time,
+/// in seconds since the unix epoch, oldest first
+pub fn games_since(since: u64) -> rusqlite::Result<Vec<GameReport>> {
+    let connection = open()?;
+    let mut games = connection.prepare(
+        "SELECT id, finished_at, seed, initial_time_ms, increment_ms, duration, moves, winner, failure
+         FROM games WHERE finished_at >= ?1 ORDER BY finished_at, id",
+    )?;
+    let mut seats = connection
+        .prepare("SELECT competitor, score FROM seats WHERE game_id = ?1 ORDER BY seat")?;
+
+    let rows = games.query_map(params![since as i64], |row| {
+        let seed: String = row.get(2)?;
+        let report = GameReport {
+            seed: seed.parse().unwrap_or_default(),
+            competitors: Vec::new(),
+            time_control: TimeControl::from_millis(
+                row.get::<_, i64>(3)? as u64,
+                row.get::<_, i64>(4)? as u64,
+            ),
+            scores: Vec::new(),
+            developments: Vec::new(),
+            winner: row.get::<_, Option<i64>>(7)?.map(|seat| seat as usize),
+            moves: row.get::<_, i64>(6)? as usize,
+            duration: row.get(5)?,
+            failure: row.get(8)?,
+            finished_at: row.get::<_, i64>(1)? as u64,
+        };
+        Ok((row.get::<_, i64>(0)?, report))
+    })?;
+
+    let mut reports = Vec::new();
+    for row in rows {
+        let (game_id, mut report) = row?;
+        let lineup = seats.query_map(params![game_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?))
+        })?;
+        for seat in lineup {
+            let (competitor, score) = seat?;
+            report.competitors.push(competitor);
+            if let Some(score) = score {
+                report.scores.push(score as u8);
+            }
+        }
+        reports.push(report);
+    }
+    trace!("Loaded {} games from the history", reports.len());
+    Ok(reports)
+}
+
+/// Parses how far back to look, such as `30m`, `12h`, `7d` or `2w`,
+/// into a number of seconds
+pub fn parse_since(since: &str) -> Option<u64> {
+    let split = since.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = since.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    amount.checked_mul(unit)
+}
+
+/// Returns whether the given competitor is the project a user asked for,
+/// either by its full path, a relative path or its directory name
+pub fn matches(competitor: &str, project: &str) -> bool {
+    competitor == project
+        || utils::project_name(competitor) == project
+        || (Path::new(project).exists() && competitor == utils::relative_to_full_path(project))
+}
+
+/// The record of one competitor over a set of games
+#[derive(Debug, Default)]
+pub struct Record {
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub failures: usize,
+}
+
+impl Record {
+    /// Adds the result of the given seat in a game to the record
+    pub fn add(&mut self, report: &GameReport, seat: usize) {
+        self.games += 1;
+        match report.winner {
+            _ if report.failure.is_some() => self.failures += 1,
+            Some(winner) if winner == seat => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    /// The share of completed games that were won
+    pub fn win_rate(&self) -> f64 {
+        let completed = self.games - self.failures;
+        if completed == 0 {
+            return 0.0;
+        }
+        self.wins as f64 / completed as f64
+    }
+}
+
+/// Returns the seat of the first competitor that matches the project, if any
+pub fn seat_of(report: &GameReport, project: &str) -> Option<usize> {
+    report
+        .competitors
+        .iter()
+        .position(|competitor| matches(competitor, project))
+}
+
+/// Returns the time `since` seconds ago, in seconds since the unix epoch
+pub fn cutoff(since: Option<u64>) -> u64 {
+    since.map_or(0, |since| results::unix_time().saturating_sub(since))
+}
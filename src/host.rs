@@ -0,0 +1,409 @@
+//! Contains the server of the arena process that plays a single game. It
+//! serves the same routes as the arena's own server, but launches the
+//! competitors and watches the game itself, so that every move is recorded
+//! with the actions the arena played, when it was played and the clock of
+//! the seat that played it
+use crate::game::{GameRecord, GameSetup, TimeControl};
+use crate::replays::{self, Card, Deck, Move, PlayerState, Position, Tokens, GEMS};
+use log::{debug, error, info, trace};
+use splendor_arena::arena::clock;
+use splendor_arena::arena::protocol::local::{
+    log_stream_connected, user_connected, Clients, GlobalArena,
+};
+use splendor_arena::arena::protocol::web::{self, Outgoing};
+use splendor_arena::arena::replay::{self, Finalized, Replay};
+use splendor_arena::tokio;
+use splendor_arena::tokio::sync::RwLock;
+use splendor_arena::{Action, Game, Gem};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::Filter;
+
+/// How long to wait between looks at the game while it is played, which
+/// bounds how precisely the time of every move is known
+const WATCH_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long to wait between launching competitors. The arena seats the
+/// competitors in the order they connect, so each gets time to connect
+/// before the next is launched
+const LAUNCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The process of every competitor launched so far, in seat order, or `None`
+/// for a competitor that failed to launch
+type Competitors = Arc<Mutex<Vec<Option<Child>>>>;
+
+/// Plays the game of the given setup, serving the arena until the process
+/// is stopped, and writes its record to the setup's record file once the
+/// game is over
+pub async fn serve(setup: GameSetup) {
+    let arena: GlobalArena = Arc::new(RwLock::new(setup.arena().build()));
+
+    // Send to stourney.com if asked to
+    let web_stream: Option<Outgoing> = if setup.web {
+        debug!("Connecting to global server...");
+        match web::start(arena.clone()).await {
+            Ok((outgoing, _)) => Some(outgoing),
+            Err(e) => {
+                error!("[-] Failed to connect to stourney.com: {}", e);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let competitors = Competitors::default();
+    tokio::spawn(launch_competitors(setup.clone(), competitors.clone()));
+    if let Some(record) = setup.record.clone() {
+        tokio::spawn(watch(
+            arena.clone(),
+            competitors,
+            setup.time_control,
+            record,
+        ));
+    }
+
+    let arena_filter = warp::any().map(move || arena.clone());
+    let clients = Clients::default();
+    let clients = warp::any().map(move || clients.clone());
+
+    let replay_post = warp::post().and(warp::path("replay"));
+    let replay_get = warp::get().and(warp::path("replay"));
+    let replay = replay_post
+        .and(warp::path("next"))
+        .and(arena_filter.clone())
+        .and_then(replay::next_move)
+        .or(replay_post
+            .and(warp::path("previous"))
+            .and(arena_filter.clone())
+            .and_then(replay::previous_move))
+        .or(replay_post
+            .and(warp::path("goto"))
+            .and(replay::json_body())
+            .and(arena_filter.clone())
+            .and_then(replay::go_to_move))
+        .or(replay_get
+            .and(warp::path("nobles"))
+            .and(arena_filter.clone())
+            .and_then(replay::board_nobles))
+        .or(replay_get
+            .and(warp::path("cards"))
+            .and(arena_filter.clone())
+            .and_then(replay::board_cards))
+        .or(replay_get
+            .and(warp::path("decks"))
+            .and(arena_filter.clone())
+            .and_then(replay::board_decks))
+        .or(replay_get
+            .and(warp::path("bank"))
+            .and(arena_filter.clone())
+            .and_then(replay::board_bank))
+        .or(replay_get
+            .and(warp::path("players"))
+            .and(arena_filter.clone())
+            .and_then(replay::board_players));
+
+    let time = warp::get()
+        .and(warp::path("time"))
+        .and(arena_filter.clone())
+        .and_then(clock::current_time_remaining);
+
+    // Logs go to a file when the game is watched on stourney.com
+    let write_to_file = setup.web;
+    let log = warp::path("log")
+        .and(warp::ws())
+        .map(move |ws: warp::ws::Ws| {
+            ws.on_upgrade(move |socket| log_stream_connected(socket, write_to_file))
+        });
+
+    let static_files = setup.static_files();
+    let splendor = warp::path("splendor").and(warp::fs::dir(static_files.clone()));
+    let static_files = warp::path("static_files").and(warp::fs::dir(static_files));
+
+    let web_stream = warp::any().map(move || web_stream.clone());
+    let game = warp::path("game")
+        .and(warp::ws())
+        .and(clients)
+        .and(arena_filter)
+        .and(web_stream)
+        .map(|ws: warp::ws::Ws, clients, arena, web_stream| {
+            ws.on_upgrade(move |socket| user_connected(socket, clients, arena, web_stream))
+        });
+
+    let routes = game
+        .or(log)
+        .or(replay)
+        .or(time)
+        .or(splendor)
+        .or(static_files);
+    debug!("Starting local server on port {}", setup.port);
+    warp::serve(routes).run(([127, 0, 0, 1], setup.port)).await;
+}
+
+/// Launches every competitor in seat order, passing each the port of the arena
+async fn launch_competitors(setup: GameSetup, competitors: Competitors) {
+    for binary in &setup.binaries {
+        tokio::time::sleep(LAUNCH_INTERVAL).await;
+        // The arena runs files ending in .py with the python interpreter
+        let mut command = if binary.ends_with(".py") {
+            let mut command = Command::new(&setup.interpreter);
+            command.arg(binary);
+            command
+        } else {
+            Command::new(binary)
+        };
+        // Competitors log through the arena, not their output
+        let launched = command
+            .arg(format!("--port={}", setup.port))
+            .stdout(Stdio::null())
+            .spawn();
+        let child = match launched {
+            Ok(child) => {
+                info!("Launched {}", binary);
+                Some(child)
+            }
+            Err(e) => {
+                error!("[-] Failed to launch {}: {}", binary, e);
+                None
+            }
+        };
+        competitors.lock().unwrap().push(child);
+    }
+}
+
+/// Returns the first seat whose competitor failed to launch or has exited
+fn exited(competitors: &Competitors) -> Option<usize> {
+    let mut competitors = competitors.lock().unwrap();
+    competitors.iter_mut().position(|child| match child {
+        Some(child) => !matches!(child.try_wait(), Ok(None)),
+        None => true,
+    })
+}
+
+/// Stops every competitor that is still running
+fn stop(competitors: &Competitors) {
+    for child in competitors.lock().unwrap().iter_mut().flatten() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Watches the game until it is over, noting when each move was finished,
+/// then writes the record of the game to `record`. A competitor that exits
+/// before the game is over forfeits it, as the arena would wait for its
+/// moves forever
+async fn watch(
+    arena: GlobalArena,
+    competitors: Competitors,
+    time_control: TimeControl,
+    record: PathBuf,
+) {
+    let mut started = None;
+    // When each move was seen to be over, in the order they were played
+    let mut finished = Vec::new();
+    loop {
+        tokio::time::sleep(WATCH_INTERVAL).await;
+        // Competitors exit once the game is over, so they are looked at
+        // before the game is, to not mistake that for a forfeit
+        let exited = exited(&competitors);
+        let arena = arena.read().await;
+        if arena.is_game_over() {
+            break;
+        }
+        if let Some(seat) = exited {
+            error!("[-] The competitor in seat {} exited", seat + 1);
+            stop(&competitors);
+            write(&GameRecord::forfeited(seat), &record);
+            return;
+        }
+        let Some(current) = arena.current_player_num() else {
+            continue;
+        };
+        let now = Instant::now();
+        started.get_or_insert(now);
+        // The move of the current seat is over once another seat is to play
+        let moves = arena.client_info().history.group_by_player();
+        let mut over = moves.len();
+        if moves.last().is_some_and(|actions| actions[0].0 == current) {
+            over -= 1;
+        }
+        finished.resize(over.max(finished.len()), now);
+    }
+    let over = Instant::now();
+    let started = started.unwrap_or(over);
+
+    // The replay only becomes available once the game is finalized. It is
+    // copied, so that stepping through it does not move the replay that the
+    // frontend is showing
+    let mut replay = loop {
+        if let Some(replay) = arena.read().await.get_replay() {
+            break replay.read().await.clone();
+        }
+        tokio::time::sleep(WATCH_INTERVAL).await;
+    };
+    let history = replayed_history(&mut replay);
+    let mut clocks = Vec::new();
+    let mut previous = started;
+    let mut moves = Vec::new();
+    let mut positions = Vec::new();
+    for (index, (seat, actions)) in group_by_seat(history).into_iter().enumerate() {
+        let played_at = finished.get(index).copied().unwrap_or(over);
+        let think_time = played_at.saturating_duration_since(previous);
+        previous = played_at;
+        // The arena adds the increment to a seat's clock as its move starts
+        if clocks.len() <= seat {
+            clocks.resize(seat + 1, time_control.initial_time);
+        }
+        clocks[seat] = (clocks[seat] + time_control.increment).saturating_sub(think_time);
+        moves.push(Move {
+            seat,
+            actions,
+            played_at: (played_at - started).as_secs_f64(),
+            think_time: think_time.as_secs_f64(),
+            clock: clocks[seat].as_secs_f64(),
+        });
+        replay.go_to_move(index as i32);
+        positions.push(position(replay.current_game()));
+    }
+
+    let Some(last) = positions.last() else {
+        error!("[-] The game is over without a single move");
+        return;
+    };
+    let game = GameRecord {
+        scores: last.players.iter().map(|player| player.points).collect(),
+        developments: replay
+            .current_game()
+            .players()
+            .iter()
+            .map(|player| player.developments().total())
+            .collect(),
+        moves,
+        positions,
+        forfeit: None,
+    };
+    write(&game, &record);
+}
+
+/// Writes the record of the game to `record`
+fn write(game: &GameRecord, record: &Path) {
+    trace!("Writing the record of the game to {}", record.display());
+    if let Err(e) = game.write(record) {
+        error!("[-] Failed to write {}: {}", record.display(), e);
+    }
+}
+
+/// Returns every action of a finished game, with the seat that played it.
+/// Replaying a game adds each action to its history twice, once as it is
+/// replayed and once as it is played, so only one of each pair is kept
+fn replayed_history(replay: &mut Replay<Finalized>) -> Vec<(usize, Action)> {
+    replay.go_to_move(i32::MAX);
+    let history = replay.current_game().history().history;
+    let doubled =
+        history.len().is_multiple_of(2) && history.chunks(2).all(|pair| pair[0] == pair[1]);
+    if doubled {
+        history.into_iter().step_by(2).collect()
+    } else {
+        history
+    }
+}
+
+/// Groups consecutive actions of the same seat into moves, the way the
+/// arena numbers the moves of its replay
+fn group_by_seat(history: Vec<(usize, Action)>) -> Vec<(usize, Vec<Action>)> {
+    let mut moves: Vec<(usize, Vec<Action>)> = Vec::new();
+    for (seat, action) in history {
+        match moves.last_mut() {
+            Some((last, actions)) if *last == seat => actions.push(action),
+            _ => moves.push((seat, vec![action])),
+        }
+    }
+    moves
+}
+
+/// Returns everything visible about the game
+fn position(game: &Game) -> Position {
+    let lookup = game.card_lookup();
+    let card = |id: u8| {
+        let card = &lookup[id as usize];
+        Card {
+            tier: card.tier() as usize - 1,
+            points: card.points() as usize,
+            color: replays::color(card.gem()),
+            cost: tokens(|gem| card.cost()[gem], false),
+        }
+    };
+    Position {
+        players: game
+            .players()
+            .iter()
+            .map(|player| PlayerState {
+                developments: tokens(|gem| player.developments()[gem], false),
+                gems: tokens(|gem| player.gems()[gem], true),
+                reserved: player.all_reserved().into_iter().map(card).collect(),
+                points: player.total_points(),
+                noble_points: player.noble_points(),
+            })
+            .collect(),
+        cards: game
+            .cards()
+            .into_iter()
+            .map(|tier| tier.into_iter().map(card).collect())
+            .collect(),
+        decks: game
+            .deck_counts()
+            .into_iter()
+            .enumerate()
+            .map(|(tier, cards)| Deck { tier, cards })
+            .collect(),
+        nobles: game
+            .nobles()
+            .iter()
+            .map(|noble| tokens(|gem| noble.requirements()[gem], false))
+            .collect(),
+        bank: tokens(|gem| game.bank()[gem], true),
+    }
+}
+
+/// Returns the non-zero amounts of every gem, with or without gold
+fn tokens(amount: impl Fn(Gem) -> i8, gold: bool) -> Tokens {
+    GEMS.iter()
+        .enumerate()
+        .filter(|&(_, &gem)| gold || gem != Gem::Gold)
+        .map(|(color, &gem)| (color, amount(gem)))
+        .filter(|&(_, amount)| amount != 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use splendor_arena::Gems;
+
+    #[test]
+    fn moves_group_the_actions_of_each_turn() {
+        let history = vec![
+            (0, Action::TakeDouble(Gem::Ruby)),
+            (0, Action::Pass),
+            (0, Action::Continue),
+            (1, Action::Reserve(3)),
+            (1, Action::Continue),
+            (0, Action::Discard(Gems::one(Gem::Ruby))),
+        ];
+        let moves = group_by_seat(history);
+        assert_eq!(moves.len(), 3);
+        assert_eq!(moves[0].0, 0);
+        assert_eq!(moves[0].1.len(), 3);
+        assert_eq!(moves[1], (1, vec![Action::Reserve(3), Action::Continue]));
+        assert_eq!(moves[2].0, 0);
+    }
+
+    #[test]
+    fn tokens_leave_out_empty_colors() {
+        let gems = Gems::one(Gem::Onyx) + Gems::one(Gem::Gold);
+        assert_eq!(tokens(|gem| gems[gem], true), [(4, 1), (5, 1)]);
+        assert_eq!(tokens(|gem| gems[gem], false), [(4, 1)]);
+    }
+}
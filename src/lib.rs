@@ -1,5 +1,20 @@
+pub mod builds;
+pub mod competitors;
 pub mod config;
 pub mod constants;
 pub mod dialogue;
+pub mod doctor;
+pub mod game;
+pub mod history;
+pub mod host;
+pub mod manifest;
+pub mod ratings;
+pub mod replays;
+pub mod results;
+pub mod seeds;
+pub mod stats;
 pub mod subcommands;
+pub mod tournament;
+pub mod update_check;
 pub mod utils;
+pub mod viewer;
@@ -0,0 +1,111 @@
+//! Contains the `stourney.project.toml` manifest, which lets a project written
+//! in any language, or a prebuilt binary, compete by declaring how to build
+//! and run it
+use crate::constants;
+use crate::utils;
+use log::{info, trace};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How to build and run a project, read from its `stourney.project.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectManifest {
+    /// The shell command that builds the project, run before it plays
+    #[serde(default)]
+    pub build: Option<String>,
+    /// The command that runs the entry point, such as `node` or `java -jar`,
+    /// or `None` to run the entry point itself
+    #[serde(default)]
+    pub run: Option<String>,
+    /// The binary or script to run, relative to the working directory
+    pub entry_point: String,
+    /// The directory the project is built and run in, relative to the project
+    #[serde(default)]
+    pub working_directory: Option<String>,
+}
+
+impl ProjectManifest {
+    /// Reads the manifest of the project in `directory`
+    pub fn load(directory: &str) -> Result<Self, String> {
+        let path = Path::new(directory).join(constants::MANIFEST_FILE_NAME);
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let manifest: ProjectManifest = toml::from_str(&contents).map_err(|e| e.to_string())?;
+        if manifest.entry_point.trim().is_empty() {
+            return Err("The entry point is empty".to_string());
+        }
+        if manifest
+            .run
+            .as_ref()
+            .is_some_and(|run| run.trim().is_empty())
+        {
+            return Err("The run command is empty".to_string());
+        }
+        Ok(manifest)
+    }
+
+    /// Returns the directory the project in `directory` is built and run in
+    pub fn working_directory(&self, directory: &str) -> PathBuf {
+        match &self.working_directory {
+            Some(working_directory) => Path::new(directory).join(working_directory),
+            None => Path::new(directory).to_path_buf(),
+        }
+    }
+
+    /// Returns the full path to the entry point of the project in `directory`
+    pub fn entry_point(&self, directory: &str) -> PathBuf {
+        self.working_directory(directory).join(&self.entry_point)
+    }
+
+    /// Runs the build command of the project in `directory`, if it has one.
+    /// Returns a summary of the build's output if it failed
+    pub fn build(&self, directory: &str) -> Result<(), String> {
+        let Some(build) = &self.build else {
+            return Ok(());
+        };
+        let working_directory = self.working_directory(directory);
+        println!("[+] Building {}...", directory);
+        trace!("Running {} in {}", build, working_directory.display());
+        let output = shell(build)
+            .current_dir(&working_directory)
+            .output()
+            .map_err(|e| format!("Failed to run `{}`: {}", build, e))?;
+        if !output.status.success() {
+            // Compilers differ in where and how they report errors, so
+            // the end of whatever the build printed is the best summary
+            let mut printed = String::from_utf8_lossy(&output.stdout).to_string();
+            printed += &String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<&str> = printed.lines().filter(|l| !l.trim().is_empty()).collect();
+            let last = &lines[lines.len().saturating_sub(LAST_LINES)..];
+            return Err(utils::summarize(last.iter().copied(), &output.status));
+        }
+        info!("[+] Project {} built successfully!", directory);
+        Ok(())
+    }
+}
+
+/// The number of lines of a failed build's output to summarize it with
+const LAST_LINES: usize = 10;
+
+/// Returns whether the project in `directory` has a manifest
+pub fn has_manifest(directory: &str) -> bool {
+    Path::new(directory)
+        .join(constants::MANIFEST_FILE_NAME)
+        .exists()
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
@@ -0,0 +1,281 @@
+//! Contains a persistent ledger of competitor ratings
+//!
+//! Ratings use the Plackett-Luce model of Weng and Lin, "A Bayesian
+//! Approximation Method for Online Ranking" (2011), which handles games
+//! of any number of players by ranking every seat rather than
+//! comparing pairs of players
+use crate::constants;
+use crate::game::{GameResult, WINNING_POINTS};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The mean rating of a competitor that has not played yet
+const INITIAL_MU: f64 = 25.0;
+
+/// The uncertainty of a competitor that has not played yet
+const INITIAL_SIGMA: f64 = INITIAL_MU / 3.0;
+
+/// The performance variance of a single game
+const BETA: f64 = INITIAL_SIGMA / 2.0;
+
+/// Uncertainty added before every game so that ratings can keep
+/// tracking competitors that change over time
+const TAU: f64 = INITIAL_MU / 300.0;
+
+/// The smallest factor the variance can shrink by in a single game
+const KAPPA: f64 = 0.0001;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The rating of a single competitor
+pub struct Rating {
+    /// The estimated skill of the competitor
+    pub mu: f64,
+    /// The uncertainty of the estimate
+    pub sigma: f64,
+    /// The number of rated games played
+    #[serde(default)]
+    pub games: usize,
+}
+
+impl ::std::default::Default for Rating {
+    fn default() -> Self {
+        Self {
+            mu: INITIAL_MU,
+            sigma: INITIAL_SIGMA,
+            games: 0,
+        }
+    }
+}
+
+impl Rating {
+    /// A conservative estimate of skill that is unlikely to be
+    /// higher than the true skill, used to order competitors
+    pub fn ordinal(&self) -> f64 {
+        self.mu - 3.0 * self.sigma
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+/// `RatingLedger` holds the ratings of every competitor that has played
+/// a rated game, keyed by alias
+///
+/// It is stored in its own file next to the config file. Ledgers from
+/// before competitors had aliases are keyed by project directory, and are
+/// renamed by `rename_competitors` when the config file is migrated
+pub struct RatingLedger {
+    #[serde(default)]
+    pub ratings: BTreeMap<String, Rating>,
+}
+
+/// Gets the ratings ledger, or an empty one if it does not exist yet
+pub fn get_ledger() -> RatingLedger {
+    let ledger = confy::load(constants::CONF_FILE_NAME, constants::RATINGS_FILE_NAME);
+    ledger.expect("[-] Failed to load ratings file")
+}
+
+/// Saves the ratings ledger
+pub fn save_ledger(ledger: RatingLedger) {
+    let stored = confy::store(
+        constants::CONF_FILE_NAME,
+        constants::RATINGS_FILE_NAME,
+        ledger,
+    );
+    stored.expect("[-] Failed to save ratings file");
+}
+
+/// Returns the place each seat finished in, starting from 0.
+/// Seats are ordered by points, then by fewest development cards,
+/// and seats that are equal on both share a place. A game where nobody
+/// reached the points needed to win is a draw, with every seat sharing
+/// first place. This is the rule `GameResult` picks the winner by
+pub fn places(scores: &[u8], developments: &[u32]) -> Vec<usize> {
+    if !scores.iter().any(|&score| score >= WINNING_POINTS) {
+        return vec![0; scores.len()];
+    }
+    let key = |seat: usize| (scores[seat], std::cmp::Reverse(developments[seat]));
+    (0..scores.len())
+        .map(|seat| {
+            (0..scores.len())
+                .filter(|&other| key(other) > key(seat))
+                .count()
+        })
+        .collect()
+}
+
+/// Updates the ratings of the players of a single game, given in seat
+/// order, with the place each of them finished in
+pub fn update(ratings: &mut [Rating], places: &[usize]) {
+    for rating in ratings.iter_mut() {
+        rating.sigma = (rating.sigma.powi(2) + TAU.powi(2)).sqrt();
+    }
+
+    let c = ratings
+        .iter()
+        .map(|r| r.sigma.powi(2) + BETA.powi(2))
+        .sum::<f64>()
+        .sqrt();
+    let strength: Vec<f64> = ratings.iter().map(|r| (r.mu / c).exp()).collect();
+    let n = ratings.len();
+
+    // The total strength of everyone who placed at or below each player
+    let sums: Vec<f64> = (0..n)
+        .map(|q| {
+            (0..n)
+                .filter(|&s| places[s] >= places[q])
+                .map(|s| strength[s])
+                .sum()
+        })
+        .collect();
+    // The number of players sharing each player's place
+    let ties: Vec<f64> = (0..n)
+        .map(|q| (0..n).filter(|&s| places[s] == places[q]).count() as f64)
+        .collect();
+
+    let mut updated = ratings.to_vec();
+    for i in 0..n {
+        let mut omega = 0.0;
+        let mut delta = 0.0;
+        for q in (0..n).filter(|&q| places[q] <= places[i]) {
+            let quotient = strength[i] / sums[q];
+            if q == i {
+                omega += (1.0 - quotient) / ties[q];
+            } else {
+                omega -= quotient / ties[q];
+            }
+            delta += quotient * (1.0 - quotient) / ties[q];
+        }
+
+        let variance = ratings[i].sigma.powi(2);
+        let gamma = ratings[i].sigma / c;
+        omega *= variance / c;
+        delta *= gamma * variance / c.powi(2);
+
+        updated[i].mu += omega;
+        updated[i].sigma = (variance * (1.0 - delta).max(KAPPA)).sqrt();
+        updated[i].games += 1;
+    }
+    ratings.clone_from_slice(&updated);
+}
+
+/// Updates and saves the ratings of the given competitors, in seat
+/// order, after they finished a game
+pub fn record_game(competitors: &[String], result: &GameResult) {
+    let mut ledger = get_ledger();
+    let mut ratings: Vec<Rating> = competitors
+        .iter()
+        .map(|c| ledger.ratings.get(c).cloned().unwrap_or_default())
+        .collect();
+
+    update(&mut ratings, &places(&result.scores, &result.developments));
+
+    for (competitor, rating) in competitors.iter().zip(ratings) {
+        info!(
+            "[+] New rating for {}: {:.2} ± {:.2}",
+            competitor, rating.mu, rating.sigma
+        );
+        ledger.ratings.insert(competitor.clone(), rating);
+    }
+    save_ledger(ledger);
+}
+
+/// Renames every competitor in the ledger that `alias` gives a new name.
+/// If the new name is rated already, the rating with more games is kept
+pub fn rename_competitors(alias: &mut dyn FnMut(&str) -> Option<String>) {
+    let mut ledger = get_ledger();
+    let mut renamed = false;
+    for key in ledger.ratings.keys().cloned().collect::<Vec<_>>() {
+        let Some(name) = alias(&key) else {
+            continue;
+        };
+        let rating = ledger.ratings.remove(&key).expect("[-] Rating disappeared");
+        info!("[+] Renaming the rating of {} to {}", key, name);
+        match ledger.ratings.get(&name) {
+            Some(existing) if existing.games >= rating.games => {}
+            _ => {
+                ledger.ratings.insert(name, rating);
+            }
+        }
+        renamed = true;
+    }
+    if renamed {
+        save_ledger(ledger);
+    }
+}
+
+/// Prints every rated competitor, from highest to lowest rating
+pub fn display_ratings() {
+    let ledger = get_ledger();
+    println!("[+] Ratings:");
+    if ledger.ratings.is_empty() {
+        println!("No rated games played yet!");
+        println!("try running \n\tstourney run\nto play some games.");
+        return;
+    }
+
+    let mut ratings: Vec<(String, Rating)> = ledger.ratings.into_iter().collect();
+    ratings.sort_by(|a, b| b.1.ordinal().total_cmp(&a.1.ordinal()));
+    println!(
+        "  {:>3}  {:>7}  {:>6}  {:>13}  {:>5}  Competitor",
+        "#", "Rating", "Mean", "Uncertainty", "Games"
+    );
+    for (place, (competitor, rating)) in ratings.iter().enumerate() {
+        println!(
+            "  {:>3}  {:>7.2}  {:>6.2}  {:>13}  {:>5}  {}",
+            place + 1,
+            rating.ordinal(),
+            rating.mu,
+            format!("± {:.2}", rating.sigma),
+            rating.games,
+            competitor
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_break_ties_by_fewer_developments() {
+        assert_eq!(places(&[15, 12, 9], &[10, 8, 6]), vec![0, 1, 2]);
+        assert_eq!(places(&[15, 15, 10], &[5, 3, 2]), vec![1, 0, 2]);
+        assert_eq!(places(&[16, 16], &[3, 3]), vec![0, 0]);
+    }
+
+    #[test]
+    fn nobody_reaching_the_winning_points_is_a_draw() {
+        assert_eq!(places(&[14, 10, 3], &[8, 6, 2]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn winner_gains_what_the_loser_loses() {
+        let mut ratings = vec![Rating::default(), Rating::default()];
+        update(&mut ratings, &[0, 1]);
+        assert!(ratings[0].mu > INITIAL_MU);
+        assert!(ratings[1].mu < INITIAL_MU);
+        assert!((ratings[0].mu - INITIAL_MU - (INITIAL_MU - ratings[1].mu)).abs() < 1e-9);
+        assert!(ratings.iter().all(|r| r.sigma < INITIAL_SIGMA));
+        assert!(ratings.iter().all(|r| r.games == 1));
+    }
+
+    #[test]
+    fn a_draw_between_equals_changes_no_means() {
+        let mut ratings = vec![Rating::default(), Rating::default(), Rating::default()];
+        update(&mut ratings, &[0, 0, 0]);
+        assert!(ratings.iter().all(|r| (r.mu - INITIAL_MU).abs() < 1e-9));
+    }
+
+    #[test]
+    fn beating_a_stronger_player_gains_more() {
+        let strong = Rating {
+            mu: 35.0,
+            ..Rating::default()
+        };
+        let mut upset = vec![Rating::default(), strong.clone()];
+        update(&mut upset, &[0, 1]);
+        let mut expected = vec![Rating::default(), Rating::default()];
+        update(&mut expected, &[0, 1]);
+        assert!(upset[0].mu > expected[0].mu);
+    }
+}
@@ -0,0 +1,385 @@
+//! Contains the replay of every finished game, stored as a JSON file
+//! in the replays directory next to the config file
+//!
+//! A replay holds every move as the actions the arena played, with when it
+//! was played and the clock of the seat that played it, and the position
+//! after every move
+use crate::competitors::Spec;
+use crate::config;
+use crate::constants;
+use crate::game::{GameResult, GameSetup, TimeControl};
+use crate::results;
+use crate::utils;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use splendor_arena::{Action, Gem, Gems};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The names of the gem colors, indexed as the arena's frontend numbers them
+pub const COLORS: [&str; 6] = ["white", "blue", "green", "red", "black", "gold"];
+
+/// The arena's gems, in the order of `COLORS`
+pub const GEMS: [Gem; 6] = [
+    Gem::Diamond,
+    Gem::Sapphire,
+    Gem::Emerald,
+    Gem::Ruby,
+    Gem::Onyx,
+    Gem::Gold,
+];
+
+/// Returns the index into `COLORS` of one of the arena's gems
+pub fn color(gem: Gem) -> usize {
+    GEMS.iter().position(|&other| other == gem).unwrap_or(0)
+}
+
+/// (color, amount) for every color with a non-zero amount
+pub type Tokens = Vec<(usize, i8)>;
+
+/// Returns the amount of the given color in a list of tokens
+pub fn count(tokens: &Tokens, color: usize) -> i8 {
+    tokens
+        .iter()
+        .filter(|&&(c, _)| c == color)
+        .map(|&(_, n)| n)
+        .sum()
+}
+
+/// A development card, either on the board or reserved by a player
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    /// The tier of the card, starting from 0
+    pub tier: usize,
+    pub points: usize,
+    /// The color of the gem the card provides
+    #[serde(alias = "colorIndex")]
+    pub color: usize,
+    #[serde(alias = "tokens")]
+    pub cost: Tokens,
+}
+
+/// The number of cards left in the deck of a tier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deck {
+    /// The tier of the deck, starting from 0
+    pub tier: usize,
+    #[serde(alias = "cardCount")]
+    pub cards: usize,
+}
+
+/// Everything visible about a single player
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerState {
+    /// The number of development cards of each color
+    pub developments: Tokens,
+    pub gems: Tokens,
+    #[serde(alias = "reservedCards")]
+    pub reserved: Vec<Card>,
+    /// The prestige points of the player, including nobles
+    #[serde(alias = "totalPoints")]
+    pub points: u8,
+    #[serde(alias = "noblePoints")]
+    pub noble_points: u8,
+}
+
+/// The state of the whole game after a move
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    /// The players, in seat order
+    pub players: Vec<PlayerState>,
+    /// The face up cards of each tier, lowest tier first
+    pub cards: Vec<Vec<Card>>,
+    pub decks: Vec<Deck>,
+    /// The requirements of each noble still on the board
+    pub nobles: Vec<Tokens>,
+    pub bank: Tokens,
+}
+
+/// A competitor of a replayed game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Competitor {
+    /// The alias of the competitor, empty in replays saved before
+    /// competitors were named
+    #[serde(default)]
+    pub alias: String,
+    /// The project directory of the competitor
+    pub path: String,
+    /// The commit the project was at, if it is a git repository
+    pub commit: Option<String>,
+}
+
+/// A single move: every action one seat played in its turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub seat: usize,
+    /// The actions in the order they were played, including the ones the
+    /// arena played for the seat when it had a single choice or ran out of time
+    pub actions: Vec<Action>,
+    /// When the move was over, in seconds since the game started
+    pub played_at: f64,
+    /// How long the seat took over the move, in seconds
+    pub think_time: f64,
+    /// The time left on the seat's clock after the move, in seconds
+    pub clock: f64,
+}
+
+impl Move {
+    /// Describes the actions of the move, such as
+    /// "bought a red card worth 1 point, paid 2 white, 1 gold"
+    pub fn description(&self) -> String {
+        let cards = splendor_arena::Card::all();
+        let card = |id: u8| {
+            let card = &cards[id as usize];
+            let worth = match card.points() {
+                0 => String::new(),
+                1 => " worth 1 point".to_string(),
+                points => format!(" worth {} points", points),
+            };
+            format!(
+                "tier {} {} card{}",
+                card.tier(),
+                COLORS[color(card.gem())],
+                worth
+            )
+        };
+        let mut parts = Vec::new();
+        for action in &self.actions {
+            match action {
+                Action::TakeDouble(gem) => parts.push(format!("took 2 {}", COLORS[color(*gem)])),
+                Action::TakeDistinct(gems) => {
+                    let mut colors: Vec<usize> = gems.iter().map(|&gem| color(gem)).collect();
+                    colors.sort();
+                    let taken: Vec<String> = colors
+                        .iter()
+                        .map(|&color| format!("1 {}", COLORS[color]))
+                        .collect();
+                    parts.push(format!("took {}", taken.join(", ")));
+                }
+                Action::Reserve(id) => parts.push(format!("reserved a {}", card(*id))),
+                Action::ReserveHidden(tier) => {
+                    parts.push(format!("reserved a card from the tier {} deck", tier + 1))
+                }
+                Action::Purchase((id, payment)) => {
+                    parts.push(format!("bought a {}", card(*id)));
+                    if payment.total() > 0 {
+                        parts.push(format!("paid {}", gems(payment)));
+                    }
+                }
+                Action::Discard(returned) => parts.push(format!("returned {}", gems(returned))),
+                Action::AttractNoble(_) => parts.push("was visited by a noble".to_string()),
+                Action::Pass | Action::Continue => {}
+            }
+        }
+        if parts.is_empty() {
+            return "passed".to_string();
+        }
+        parts.join(", ")
+    }
+}
+
+/// Returns a list of gems such as "2 white, 1 gold"
+fn gems(gems: &Gems) -> String {
+    let counts: Vec<String> = GEMS
+        .iter()
+        .enumerate()
+        .filter(|&(_, &gem)| gems[gem] > 0)
+        .map(|(color, &gem)| format!("{} {}", gems[gem], COLORS[color]))
+        .collect();
+    counts.join(", ")
+}
+
+/// A move of a replay saved before moves were recorded,
+/// described from the difference between two positions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DescribedMove {
+    /// The seat that played the move, or `None` if no player changed
+    pub seat: Option<usize>,
+    pub description: String,
+}
+
+/// A finished game, with enough information to step through every move
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub id: String,
+    pub seed: u64,
+    /// The competitors, in seat order
+    pub competitors: Vec<Competitor>,
+    pub time_control: TimeControl,
+    /// When the game finished, in seconds since the unix epoch
+    pub finished_at: u64,
+    /// How long the game took, in seconds
+    pub duration: f64,
+    pub scores: Vec<u8>,
+    /// The winning seat, or `None` if the game was drawn
+    pub winner: Option<usize>,
+    /// Every move, `moves[i]` led to `positions[i]`
+    #[serde(default)]
+    pub moves: Vec<Move>,
+    /// The moves of replays saved before moves were recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<DescribedMove>,
+    /// The position after every move. The arena has no position
+    /// from before the first move
+    pub positions: Vec<Position>,
+}
+
+impl Replay {
+    pub fn new(setup: &GameSetup, result: &GameResult, duration: Duration) -> Self {
+        let finished_at = results::unix_time();
+        let competitors = setup
+            .competitors
+            .iter()
+            .zip(&setup.projects)
+            .map(|(alias, project)| {
+                let spec = Spec::parse(project);
+                Competitor {
+                    alias: alias.clone(),
+                    path: spec.path.clone(),
+                    commit: spec.commit(),
+                }
+            })
+            .collect();
+        Replay {
+            id: format!("{}-{}", finished_at, setup.seed),
+            seed: setup.seed,
+            competitors,
+            time_control: setup.time_control,
+            finished_at,
+            duration: duration.as_secs_f64(),
+            scores: result.scores.clone(),
+            winner: result.winner,
+            moves: result.moves.clone(),
+            actions: Vec::new(),
+            positions: result.positions.clone(),
+        }
+    }
+
+    /// Returns the short name of the competitor in the given seat
+    pub fn name(&self, seat: usize) -> String {
+        let competitor = &self.competitors[seat];
+        if competitor.alias.is_empty() {
+            utils::project_name(&competitor.path)
+        } else {
+            competitor.alias.clone()
+        }
+    }
+
+    /// Returns a single line with the scores of every competitor
+    pub fn scoreline(&self) -> String {
+        let scores: Vec<String> = (0..self.competitors.len())
+            .map(|seat| format!("{} {}", self.name(seat), self.scores[seat]))
+            .collect();
+        scores.join("  ")
+    }
+
+    /// Returns the number of moves played
+    pub fn move_count(&self) -> usize {
+        self.moves.len().max(self.actions.len())
+    }
+
+    /// Returns the seat that played the move at `index`, if it is known,
+    /// and a description of the move
+    pub fn describe(&self, index: usize) -> (Option<usize>, String) {
+        if let Some(played) = self.moves.get(index) {
+            return (Some(played.seat), played.description());
+        }
+        match self.actions.get(index) {
+            Some(described) => (described.seat, described.description.clone()),
+            None => (None, String::new()),
+        }
+    }
+}
+
+/// Returns the directory replays are stored in
+pub fn directory() -> PathBuf {
+    config::config_directory().join(constants::REPLAYS_DIRECTORY)
+}
+
+/// Writes a replay of the given game to the replays directory,
+/// returning its id
+pub fn save(setup: &GameSetup, result: &GameResult, duration: Duration) -> Option<String> {
+    let replay = Replay::new(setup, result, duration);
+    let path = directory().join(format!("{}.json", replay.id));
+    let contents = serde_json::to_string(&replay).expect("[-] Failed to serialize replay");
+    let written = fs::create_dir_all(directory()).and_then(|_| fs::write(&path, contents));
+    match written {
+        Ok(_) => Some(replay.id),
+        Err(e) => {
+            warn!("[-] Failed to write replay {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Returns the ids of every stored replay, oldest first
+pub fn ids() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(directory()) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            match path.extension() {
+                Some(extension) if extension == "json" => {
+                    Some(path.file_stem()?.to_string_lossy().to_string())
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    // Ids start with the time the game finished
+    ids.sort_by_key(|id| {
+        let finished_at = id.split('-').next().and_then(|t| t.parse::<u64>().ok());
+        (finished_at, id.clone())
+    });
+    ids
+}
+
+/// Loads the replay with the given id, or the only replay whose id
+/// starts with it
+pub fn load(id: &str) -> Result<Replay, String> {
+    let ids = ids();
+    let matching: Vec<&String> = match ids.iter().find(|other| *other == id) {
+        Some(exact) => vec![exact],
+        None => ids.iter().filter(|other| other.starts_with(id)).collect(),
+    };
+    let id = match matching[..] {
+        [id] => id,
+        [] => return Err(format!("No replay found with id {}", id)),
+        _ => return Err(format!("{} replays match {}", matching.len(), id)),
+    };
+    load_file(&directory().join(format!("{}.json", id)))
+}
+
+/// Loads the replay stored in the given file
+pub fn load_file(path: &Path) -> Result<Replay, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| {
+        trace!("Failed to parse {}: {}", path.display(), e);
+        format!("{} is not a valid replay", path.display())
+    })
+}
+
+/// Deletes every replay except the `keep` most recent ones,
+/// returning the number deleted
+pub fn prune(keep: usize) -> usize {
+    let ids = ids();
+    let excess = ids.len().saturating_sub(keep);
+    ids[..excess]
+        .iter()
+        .filter(|id| {
+            let path = directory().join(format!("{}.json", id));
+            match fs::remove_file(&path) {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!("[-] Failed to delete {}: {}", path.display(), e);
+                    false
+                }
+            }
+        })
+        .count()
+}
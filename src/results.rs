@@ -0,0 +1,428 @@
+//! Contains the report of each finished game and the files
+//! that reports are written to during a batch of games
+use crate::config;
+use crate::constants;
+use crate::game::{GameError, GameResult, GameSetup, TimeControl};
+use crate::ratings;
+use crate::utils;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Returns the current time in seconds since the unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Everything known about a single game once it is over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameReport {
+    pub seed: u64,
+    /// The alias of each competitor, in seat order
+    pub competitors: Vec<String>,
+    pub time_control: TimeControl,
+    /// The prestige points of each seat, empty if the game failed
+    pub scores: Vec<u8>,
+    /// The number of development cards of each seat, empty if the game failed
+    #[serde(default)]
+    pub developments: Vec<u32>,
+    /// The winning seat, or `None` if the game was drawn or failed
+    pub winner: Option<usize>,
+    /// The number of moves played, zero if the game failed
+    pub moves: usize,
+    /// How long the game took, in seconds
+    pub duration: f64,
+    /// Why the game failed, if it did
+    pub failure: Option<String>,
+    /// When the game finished, in seconds since the unix epoch
+    pub finished_at: u64,
+}
+
+impl GameReport {
+    pub fn new(
+        setup: &GameSetup,
+        outcome: &Result<GameResult, GameError>,
+        duration: Duration,
+    ) -> Self {
+        let (scores, developments, winner, moves, failure) = match outcome {
+            Ok(result) => (
+                result.scores.clone(),
+                result.developments.clone(),
+                result.winner,
+                result.moves.len(),
+                None,
+            ),
+            Err(e) => (Vec::new(), Vec::new(), None, 0, Some(e.to_string())),
+        };
+        GameReport {
+            seed: setup.seed,
+            competitors: setup.competitors.clone(),
+            time_control: setup.time_control,
+            scores,
+            developments,
+            winner,
+            moves,
+            duration: duration.as_secs_f64(),
+            failure,
+            finished_at: unix_time(),
+        }
+    }
+
+    /// Returns a single line describing the game, as the `number`th of `total` games
+    pub fn summary(&self, number: usize, total: usize) -> String {
+        let header = format!(
+            "Game {}/{}  seed {}  {:.1}s",
+            number, total, self.seed, self.duration
+        );
+        if let Some(failure) = &self.failure {
+            return format!("[-] {}  failed: {}", header, failure);
+        }
+
+        let scores: Vec<String> = self
+            .competitors
+            .iter()
+            .zip(&self.scores)
+            .map(|(competitor, score)| format!("{} {}", utils::project_name(competitor), score))
+            .collect();
+        let winner = match self.winner {
+            Some(seat) => utils::project_name(&self.competitors[seat]),
+            None => "draw".to_string(),
+        };
+        format!(
+            "[+] {}  {} moves  {}  winner: {}",
+            header,
+            self.moves,
+            scores.join("  "),
+            winner
+        )
+    }
+}
+
+/// A file of game reports, one JSON object per line, that is
+/// written to as each game finishes
+pub struct ResultsFile {
+    path: PathBuf,
+    file: fs::File,
+}
+
+impl ResultsFile {
+    /// Creates a new results file in the results directory,
+    /// named after the time the batch started
+    pub fn create() -> Option<Self> {
+        let directory = config::config_directory().join(constants::RESULTS_DIRECTORY);
+        let path = directory.join(format!("{}.jsonl", unix_time()));
+        let file = fs::create_dir_all(&directory)
+            .and_then(|_| fs::OpenOptions::new().create(true).append(true).open(&path));
+        match file {
+            Ok(file) => Some(ResultsFile { path, file }),
+            Err(e) => {
+                error!(
+                    "[-] Failed to create results file {}: {}",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a game report to the file
+    pub fn write(&mut self, report: &GameReport) {
+        let line = serde_json::to_string(report).expect("[-] Failed to serialize game report");
+        if let Err(e) = writeln!(self.file, "{}", line) {
+            warn!("[-] Failed to write to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// The formats results can be exported in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// The names of the formats, as given on the command line
+    pub const NAMES: [&'static str; 2] = ["csv", "json"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the extension of a file, defaulting to csv
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension == "json" => ExportFormat::Json,
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+/// A row of the exported games, seats and places are numbered from 1
+#[derive(Debug, Serialize)]
+struct GameRow {
+    game: usize,
+    seed: u64,
+    finished_at: u64,
+    players: usize,
+    initial_time: f64,
+    increment: f64,
+    duration: f64,
+    moves: usize,
+    winner_seat: Option<usize>,
+    winner: Option<String>,
+    failure: Option<String>,
+}
+
+impl GameRow {
+    const HEADER: &'static str = "game,seed,finished_at,players,initial_time,increment,duration,moves,winner_seat,winner,failure";
+
+    fn new(number: usize, report: &GameReport) -> Self {
+        GameRow {
+            game: number,
+            seed: report.seed,
+            finished_at: report.finished_at,
+            players: report.competitors.len(),
+            initial_time: report.time_control.initial_time.as_secs_f64(),
+            increment: report.time_control.increment.as_secs_f64(),
+            duration: report.duration,
+            moves: report.moves,
+            winner_seat: report.winner.map(|seat| seat + 1),
+            winner: report.winner.map(|seat| report.competitors[seat].clone()),
+            failure: report.failure.clone(),
+        }
+    }
+
+    fn csv(&self) -> String {
+        csv_line(&[
+            self.game.to_string(),
+            self.seed.to_string(),
+            self.finished_at.to_string(),
+            self.players.to_string(),
+            self.initial_time.to_string(),
+            self.increment.to_string(),
+            self.duration.to_string(),
+            self.moves.to_string(),
+            optional(&self.winner_seat),
+            optional(&self.winner),
+            optional(&self.failure),
+        ])
+    }
+}
+
+/// A row of the exported seats, one for each player of each game
+#[derive(Debug, Serialize)]
+struct SeatRow {
+    game: usize,
+    seed: u64,
+    seat: usize,
+    competitor: String,
+    name: String,
+    score: Option<u8>,
+    developments: Option<u32>,
+    place: Option<usize>,
+    won: bool,
+}
+
+impl SeatRow {
+    const HEADER: &'static str = "game,seed,seat,competitor,name,score,developments,place,won";
+
+    fn rows(number: usize, report: &GameReport) -> Vec<Self> {
+        let complete = report.scores.len() == report.competitors.len()
+            && report.developments.len() == report.competitors.len();
+        let places = if complete {
+            ratings::places(&report.scores, &report.developments)
+        } else {
+            Vec::new()
+        };
+        report
+            .competitors
+            .iter()
+            .enumerate()
+            .map(|(seat, competitor)| SeatRow {
+                game: number,
+                seed: report.seed,
+                seat: seat + 1,
+                competitor: competitor.clone(),
+                name: utils::project_name(competitor),
+                score: report.scores.get(seat).copied(),
+                developments: report.developments.get(seat).copied(),
+                place: places.get(seat).map(|place| place + 1),
+                won: report.winner == Some(seat),
+            })
+            .collect()
+    }
+
+    fn csv(&self) -> String {
+        csv_line(&[
+            self.game.to_string(),
+            self.seed.to_string(),
+            self.seat.to_string(),
+            self.competitor.clone(),
+            self.name.clone(),
+            optional(&self.score),
+            optional(&self.developments),
+            optional(&self.place),
+            self.won.to_string(),
+        ])
+    }
+}
+
+/// Returns an optional value as a csv field, empty if there is none
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+/// Joins fields into a line of csv, quoting the fields that need it
+fn csv_line(fields: &[String]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    fields.join(",")
+}
+
+/// Results exported to a file chosen by the user, with a table of games
+/// and a table of seats. As csv, the games are written to the file and
+/// the seats next to it, as json both tables are written to the file
+pub struct ResultsExport {
+    path: PathBuf,
+    format: ExportFormat,
+    /// The games and seats files, written as each game finishes
+    csv: Option<(fs::File, fs::File)>,
+    games: Vec<GameRow>,
+    seats: Vec<SeatRow>,
+}
+
+impl ResultsExport {
+    /// Creates the export file, in the given format or
+    /// the one that matches its extension
+    pub fn create(path: &str, format: Option<&str>) -> Option<Self> {
+        let path = PathBuf::from(path);
+        let format = match format {
+            Some(name) => match ExportFormat::from_name(name) {
+                Some(format) => format,
+                None => {
+                    error!("[-] Unknown results format {}", name);
+                    error!("[-] Expected one of {}", ExportFormat::NAMES.join(", "));
+                    return None;
+                }
+            },
+            None => ExportFormat::from_path(&path),
+        };
+
+        let csv = match format {
+            ExportFormat::Csv => {
+                let games = create_with_header(&path, GameRow::HEADER)?;
+                let seats = create_with_header(&seats_path(&path), SeatRow::HEADER)?;
+                Some((games, seats))
+            }
+            ExportFormat::Json => {
+                // Fail now rather than after every game has been played
+                create_with_header(&path, "")?;
+                None
+            }
+        };
+        Some(ResultsExport {
+            path,
+            format,
+            csv,
+            games: Vec::new(),
+            seats: Vec::new(),
+        })
+    }
+
+    /// Adds the `number`th game to the export
+    pub fn add(&mut self, number: usize, report: &GameReport) {
+        let game = GameRow::new(number, report);
+        let seats = SeatRow::rows(number, report);
+        let Some((games_file, seats_file)) = &mut self.csv else {
+            self.games.push(game);
+            self.seats.extend(seats);
+            return;
+        };
+
+        let mut written = writeln!(games_file, "{}", game.csv());
+        for seat in seats {
+            written = written.and_then(|_| writeln!(seats_file, "{}", seat.csv()));
+        }
+        if let Err(e) = written {
+            warn!("[-] Failed to export game {}: {}", number, e);
+        }
+    }
+
+    /// Finishes writing the export and tells the user where it is
+    pub fn finish(self) {
+        if self.format == ExportFormat::Json {
+            let tables = Tables {
+                games: &self.games,
+                seats: &self.seats,
+            };
+            let contents =
+                serde_json::to_string_pretty(&tables).expect("[-] Failed to serialize the results");
+            if let Err(e) = fs::write(&self.path, contents) {
+                error!("[-] Failed to write {}: {}", self.path.display(), e);
+                return;
+            }
+            println!("[+] Results exported to {}", self.path.display());
+            return;
+        }
+        println!(
+            "[+] Results exported to {} and {}",
+            self.path.display(),
+            seats_path(&self.path).display()
+        );
+    }
+}
+
+/// Both tables of a json export
+#[derive(Serialize)]
+struct Tables<'a> {
+    games: &'a [GameRow],
+    seats: &'a [SeatRow],
+}
+
+/// Returns the path of the seats table of a csv export, `results.csv`
+/// has its seats in `results.seats.csv`
+fn seats_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.seats.csv", stem))
+}
+
+/// Creates a file, replacing any existing one, and writes a header line to it
+fn create_with_header(path: &Path, header: &str) -> Option<fs::File> {
+    let created = fs::File::create(path).and_then(|mut file| {
+        if !header.is_empty() {
+            writeln!(file, "{}", header)?;
+        }
+        Ok::<_, io::Error>(file)
+    });
+    match created {
+        Ok(file) => Some(file),
+        Err(e) => {
+            error!("[-] Failed to create {}: {}", path.display(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Contains a log of the seed of every game that has been launched,
+//! with the competitors and time control it was played with
+//!
+//! Competitors receive the seed through the `STOURNEY_SEED` environment
+//! variable so they can seed their own random number generators.
+//! The arena deals cards with its own unseeded generator, so the seed
+//! does not decide the deal and a game cannot be replayed from it. Seeding
+//! the deal and rerunning a game are blocked until `splendor_arena` can be
+//! seeded
+use crate::config;
+use crate::constants;
+use crate::game::{GameSetup, TimeControl};
+use crate::results;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A single game in the seed log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedRecord {
+    pub seed: u64,
+    /// The alias of each competitor, in seat order
+    pub competitors: Vec<String>,
+    pub time_control: TimeControl,
+    /// When the game was launched, in seconds since the unix epoch
+    pub launched_at: u64,
+}
+
+/// Returns a new random seed
+pub fn random_seed() -> u64 {
+    // Every RandomState is seeded from the operating system
+    RandomState::new().build_hasher().finish()
+}
+
+/// Returns the path of the seed log
+fn log_path() -> PathBuf {
+    config::config_directory().join(constants::SEEDS_FILE_NAME)
+}
+
+/// Appends the given game to the seed log
+pub fn record(setup: &GameSetup) {
+    let launched_at = results::unix_time();
+    let record = SeedRecord {
+        seed: setup.seed,
+        competitors: setup.competitors.clone(),
+        time_control: setup.time_control,
+        launched_at,
+    };
+    let line = serde_json::to_string(&record).expect("[-] Failed to serialize seed record");
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path());
+    // Write the whole line at once so that records appended by
+    // concurrent games are never interleaved
+    let written = file.and_then(|mut file| file.write_all(format!("{}\n", line).as_bytes()));
+    if let Err(e) = written {
+        warn!("[-] Failed to record seed {}: {}", setup.seed, e);
+    }
+}
+
+/// Renames every competitor in the seed log that `alias` gives a new name
+pub fn rename_competitors(alias: &mut dyn FnMut(&str) -> Option<String>) {
+    let Ok(log) = fs::read_to_string(log_path()) else {
+        return;
+    };
+    let mut renamed = false;
+    let mut lines = Vec::new();
+    for line in log.lines() {
+        let Ok(mut record) = serde_json::from_str::<SeedRecord>(line) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        for competitor in record.competitors.iter_mut() {
+            if let Some(name) = alias(competitor) {
+                *competitor = name;
+                renamed = true;
+            }
+        }
+        lines.push(serde_json::to_string(&record).expect("[-] Failed to serialize seed record"));
+    }
+    if !renamed {
+        return;
+    }
+    if let Err(e) = fs::write(log_path(), lines.join("\n") + "\n") {
+        warn!("[-] Failed to rename competitors in the seed log: {}", e);
+    }
+}
@@ -0,0 +1,250 @@
+//! Contains the statistics used to tell whether a difference between
+//! competitors is real or could just be noise from a small sample
+
+/// The z-score of a two-sided 95% confidence interval
+pub const Z_95: f64 = 1.959964;
+
+/// The significance level p-values are compared against
+pub const SIGNIFICANCE: f64 = 0.05;
+
+/// The fewest values a confidence interval is shown for. Smaller samples give
+/// intervals too wide to mean anything, or none at all for a single value
+pub const MIN_SAMPLE: usize = 5;
+
+/// Returns the 95% Wilson score interval of a proportion of `successes`
+/// out of `trials`, which stays within [0, 1] even for small samples
+pub fn wilson_interval(successes: f64, trials: usize) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let n = trials as f64;
+    let p = successes / n;
+    let z2 = Z_95 * Z_95;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = Z_95 / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+/// Returns the mean of a sample and the half width of its 95% confidence interval,
+/// given the number of values, their sum and the sum of their squares
+pub fn mean_interval(count: usize, sum: f64, sum_of_squares: f64) -> (f64, f64) {
+    if count == 0 {
+        return (0.0, 0.0);
+    }
+    let n = count as f64;
+    let mean = sum / n;
+    if count == 1 {
+        return (mean, f64::INFINITY);
+    }
+    let variance = ((sum_of_squares - n * mean * mean) / (n - 1.0)).max(0.0);
+    (mean, Z_95 * (variance / n).sqrt())
+}
+
+/// Returns the likelihood that the first of two competitors is the stronger one,
+/// given the games each of them won against the other. Draws carry no information
+pub fn likelihood_of_superiority(wins: usize, losses: usize) -> f64 {
+    if wins + losses == 0 {
+        return 0.5;
+    }
+    let (wins, losses) = (wins as f64, losses as f64);
+    0.5 * (1.0 + erf((wins - losses) / (2.0 * (wins + losses)).sqrt()))
+}
+
+/// Returns the two-sided p-value of a sign test, the probability of a split of
+/// decisive games at least as uneven as `wins` to `losses` if both competitors
+/// were equally strong
+pub fn sign_test(wins: usize, losses: usize) -> f64 {
+    let n = wins + losses;
+    if n == 0 {
+        return 1.0;
+    }
+    // Work with logarithms, as 0.5^n underflows for long matches
+    let mut ln_factorials = vec![0.0; n + 1];
+    for i in 1..=n {
+        ln_factorials[i] = ln_factorials[i - 1] + (i as f64).ln();
+    }
+    let probability = |k: usize| {
+        (ln_factorials[n] - ln_factorials[k] - ln_factorials[n - k] - n as f64 * 2f64.ln()).exp()
+    };
+    let fewer = wins.min(losses);
+    let tail: f64 = (0..=fewer).map(probability).sum();
+    (2.0 * tail).min(1.0)
+}
+
+/// The error function, using the approximation 7.1.26 of Abramowitz and Stegun
+/// which is accurate to 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = x.signum();
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - polynomial * (-x * x).exp())
+}
+
+/// Returns the expected score of a competitor that is `elo` points
+/// stronger than its opponent, under the logistic Elo model
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The hypothesis a sequential probability ratio test settled on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtDecision {
+    /// The candidate is no stronger than `elo0`
+    AcceptH0,
+    /// The candidate is at least `elo1` stronger
+    AcceptH1,
+}
+
+/// A sequential probability ratio test between the hypotheses that a
+/// candidate is `elo0` or `elo1` Elo stronger than its base, with the
+/// chance of wrongly accepting `elo1` at most `alpha` and the chance of
+/// wrongly accepting `elo0` at most `beta`
+#[derive(Debug, Clone, Copy)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// The log-likelihood ratios at which the test accepts H0 and H1
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Returns the log-likelihood ratio of H1 over H0 after the candidate
+    /// won, drew and lost the given number of games, using the normal
+    /// approximation of the score per game
+    pub fn llr(&self, wins: usize, draws: usize, losses: usize) -> f64 {
+        // Half a game of each outcome keeps the variance from being zero
+        // while one side has won every game so far, which is common in Splendor
+        let (w, d, l) = (wins as f64 + 0.5, draws as f64 + 0.5, losses as f64 + 0.5);
+        let n = w + d + l;
+        let mean = (w + 0.5 * d) / n;
+        let variance = (w * (1.0 - mean).powi(2) + d * (0.5 - mean).powi(2) + l * mean.powi(2)) / n;
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (s1 - s0) * (2.0 * mean - s0 - s1) * n / (2.0 * variance)
+    }
+
+    /// Returns the hypothesis the test accepts, or `None` if it needs more games
+    pub fn decide(&self, wins: usize, draws: usize, losses: usize) -> Option<SprtDecision> {
+        let llr = self.llr(wins, draws, losses);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(SprtDecision::AcceptH1)
+        } else if llr <= lower {
+            Some(SprtDecision::AcceptH0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn wilson_interval_matches_known_values() {
+        let (low, high) = wilson_interval(5.0, 10);
+        assert_close(low, 0.2366);
+        assert_close(high, 0.7634);
+        let (low, high) = wilson_interval(0.0, 10);
+        assert_close(low, 0.0);
+        assert_close(high, 0.2775);
+        assert_eq!(wilson_interval(0.0, 0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn mean_interval_of_a_small_sample() {
+        // The values 1, 2, 3 and 4
+        let (mean, half_width) = mean_interval(4, 10.0, 30.0);
+        assert_close(mean, 2.5);
+        assert_close(half_width, Z_95 * (5.0f64 / 12.0).sqrt());
+        assert_eq!(mean_interval(1, 3.0, 9.0), (3.0, f64::INFINITY));
+        assert_eq!(mean_interval(0, 0.0, 0.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn erf_matches_known_values() {
+        assert_close(erf(0.0), 0.0);
+        assert_close(erf(1.0), 0.842701);
+        assert_close(erf(-1.0), -0.842701);
+        assert_close(erf(2.0), 0.995322);
+    }
+
+    #[test]
+    fn likelihood_of_superiority_matches_known_values() {
+        assert_close(likelihood_of_superiority(0, 0), 0.5);
+        assert_close(likelihood_of_superiority(10, 10), 0.5);
+        assert_close(likelihood_of_superiority(60, 40), 0.977250);
+        assert_close(likelihood_of_superiority(40, 60), 0.022750);
+    }
+
+    #[test]
+    fn sign_test_matches_the_binomial_distribution() {
+        assert_close(sign_test(0, 0), 1.0);
+        assert_close(sign_test(5, 5), 1.0);
+        // P(X <= 1) for 10 fair coin flips is 11 / 1024
+        assert_close(sign_test(9, 1), 22.0 / 1024.0);
+        assert_close(sign_test(1, 9), 22.0 / 1024.0);
+        assert_close(sign_test(10, 0), 2.0 / 1024.0);
+        // Long matches do not underflow
+        assert!(sign_test(1500, 1500) > 0.9);
+    }
+
+    #[test]
+    fn expected_score_follows_the_elo_model() {
+        assert_close(expected_score(0.0), 0.5);
+        assert_close(expected_score(400.0), 10.0 / 11.0);
+        assert_close(expected_score(-400.0), 1.0 / 11.0);
+    }
+
+    const SPRT: Sprt = Sprt {
+        elo0: 0.0,
+        elo1: 10.0,
+        alpha: 0.05,
+        beta: 0.05,
+    };
+
+    #[test]
+    fn sprt_bounds() {
+        let (lower, upper) = SPRT.bounds();
+        assert_close(lower, -2.944439);
+        assert_close(upper, 2.944439);
+    }
+
+    #[test]
+    fn sprt_llr_follows_the_score() {
+        assert!(SPRT.llr(0, 0, 0).abs() < 0.1);
+        assert!(SPRT.llr(30, 10, 10) > 0.0);
+        assert!(SPRT.llr(10, 10, 30) < 0.0);
+        assert!(SPRT.llr(40, 10, 10) > SPRT.llr(30, 10, 10));
+        // Winning every game keeps the variance, and so the ratio, finite
+        assert!(SPRT.llr(20, 0, 0).is_finite());
+    }
+
+    #[test]
+    fn sprt_decides_only_past_a_bound() {
+        assert_eq!(SPRT.decide(0, 0, 0), None);
+        assert_eq!(SPRT.decide(11, 0, 10), None);
+        assert_eq!(SPRT.decide(200, 0, 0), Some(SprtDecision::AcceptH1));
+        assert_eq!(SPRT.decide(0, 0, 200), Some(SprtDecision::AcceptH0));
+    }
+}
@@ -1,13 +1,31 @@
+use crate::builds;
+use crate::competitors::{self, Competitor};
 use crate::config;
 use crate::constants;
 use crate::dialogue;
+use crate::doctor;
+use crate::game::{self, GameError, GameResult, GameSetup, TimeControl};
+use crate::history;
+use crate::host;
+use crate::manifest::ProjectManifest;
+use crate::ratings;
+use crate::replays;
+use crate::results;
+use crate::seeds;
+use crate::stats::{self, Sprt, SprtDecision};
+use crate::tournament;
+use crate::update_check;
 use crate::utils;
+use crate::viewer;
 use log::*;
-use splendor_arena::ArenaBuilder;
+use splendor_arena::tokio;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
-use std::time::Duration;
-use splendor_arena::tungstenite;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Prints the version of the stourney binary
 pub fn version_command() {
@@ -27,16 +45,16 @@ pub fn new_command(directory: &str) {
 
     if !Path::new(&directory).exists() {
         // If the path does not exist, create the empty directory
-        fs::create_dir(&directory).expect("[-] Failed to create directory");
+        fs::create_dir(directory).expect("[-] Failed to create directory");
     }
 
     if Path::new(&directory).is_dir() {
         // If the path exists and it is a directory,
         // check if it is empty
-        let dir_contents = fs::read_dir(&directory).expect("[-] Failed to read directory contents");
+        let dir_contents = fs::read_dir(directory).expect("[-] Failed to read directory contents");
         if dir_contents.count() > 0 {
             if dialogue::confirm_delete() {
-                fs::remove_dir_all(&directory).expect("[-] Failed to remove directory");
+                fs::remove_dir_all(directory).expect("[-] Failed to remove directory");
             } else {
                 return;
             }
@@ -46,9 +64,9 @@ pub fn new_command(directory: &str) {
         return;
     }
 
-    if utils::create_project(&directory) {
+    if utils::create_project(directory) {
         println!("[+] Project created successfully!");
-        config::add_to_recents(&directory);
+        config::add_to_recents(directory);
     } else {
         error!("[-] Failed to create project");
     }
@@ -66,11 +84,11 @@ pub fn configure_command() {
         }
     }
 
-    let mut cfg = config::get_config();
+    let mut cfg = config::global_config();
     cfg.selected_projects = competitors.clone();
     config::save_config(cfg);
 
-    println!("");
+    println!();
     println!("[+] Configuration saved successfully!");
     config::display_competitors();
     println!("[+] To run the project, try: \n\tstourney run");
@@ -79,75 +97,1219 @@ pub fn configure_command() {
 /// Displays the current competitors in the configuration
 pub fn show_competitors() {
     config::display_competitors();
+    config::display_arena_settings();
 }
 
-/// Sets up the initial arena with configurable settings
-fn setup_arena() -> Result<ArenaBuilder, ()> {
+/// Adds a project as a named competitor without any prompts,
+/// returning whether it was added
+pub fn config_add_command(project: &str, alias: Option<&str>, language: Option<&str>) -> bool {
+    let spec = competitors::Spec::parse(project);
+    if !spec.check(true) {
+        error!("[-] Invalid project directory {}", project);
+        return false;
+    }
+    let spec = spec.full();
+    let cfg = config::get_config();
+    let alias = match alias {
+        Some(alias) if !competitors::valid_alias(alias) => {
+            error!("[-] Invalid alias {}", alias);
+            error!("[-] Use only letters, digits, '-', '_', '.' and '@'");
+            return false;
+        }
+        Some(alias) if cfg.competitors.iter().any(|c| c.alias == alias) => {
+            error!("[-] A competitor named {} already exists", alias);
+            error!(
+                "[-] Remove it first with `stourney config remove {}`",
+                alias
+            );
+            return false;
+        }
+        Some(alias) => alias.to_string(),
+        None => competitors::unique_alias(&spec, &cfg.competitors),
+    };
+    let competitor = Competitor {
+        alias,
+        path: spec.path.clone(),
+        language: language.map(str::to_string),
+        commit: spec.revision.clone(),
+        args: Vec::new(),
+        env: Default::default(),
+    };
+    config::add_to_recents(&spec.path);
+    println!("[+] Added competitor {}", competitor);
+    config::save_competitor(competitor);
+    true
+}
+
+/// Removes a named competitor, and from the selected competitors,
+/// returning whether it existed
+pub fn config_remove_command(alias: &str) -> bool {
+    let mut cfg = config::global_config();
+    let Some(index) = cfg.competitors.iter().position(|c| c.alias == alias) else {
+        error!("[-] No competitor named {}", alias);
+        return false;
+    };
+    let competitor = cfg.competitors.remove(index);
+    cfg.selected_projects.retain(|selected| selected != alias);
+    config::save_config(cfg);
+    println!("[+] Removed competitor {}", competitor);
+    true
+}
+
+/// Prints the value of a setting, returning whether it exists
+pub fn config_get_command(key: &str) -> bool {
+    match config::get_setting(&config::get_config(), key) {
+        Ok(value) => {
+            println!("{}", value);
+            true
+        }
+        Err(e) => {
+            error!("[-] {}", e);
+            false
+        }
+    }
+}
+
+/// Changes a setting, returning whether the new value was valid
+pub fn config_set_command(key: &str, value: &str) -> bool {
+    let mut cfg = config::global_config();
+    if let Err(e) = config::set_setting(&mut cfg, key, value) {
+        error!("[-] {}", e);
+        return false;
+    }
+    config::save_config(cfg);
+    println!("[+] Set {} to {}", key, value);
+    warn_if_overridden(key);
+    true
+}
+
+/// Lists the profiles of the global and workspace config files
+pub fn profiles_list_command() {
+    let cfg = config::global_config();
+    let workspace = config::current_workspace();
+    let mut profiles: Vec<(&String, &config::Profile)> = cfg.profiles.iter().collect();
+    if let Some(workspace) = workspace {
+        profiles.retain(|(name, _)| !workspace.profiles.contains_key(*name));
+        profiles.extend(&workspace.profiles);
+        profiles.sort_by_key(|(name, _)| *name);
+    }
+    if profiles.is_empty() {
+        println!("[+] No profiles saved yet!");
+        println!(
+            "try running \n\tstourney config profiles save <name>\nto save the current lineup"
+        );
+        return;
+    }
+    println!("[+] Profiles:");
+    for (name, profile) in profiles {
+        let source = match workspace {
+            Some(workspace) if workspace.profiles.contains_key(name) => {
+                format!(" (from {})", workspace.path.display())
+            }
+            _ => String::new(),
+        };
+        println!("  - {}{}", name, source);
+        if let Some(lineup) = &profile.lineup {
+            println!("      lineup: {}", lineup.join(" "));
+        }
+        if let Some(time_control) = &profile.time_control {
+            println!("      time control: {}", time_control);
+        }
+        if profile.initial_time_ms.is_some() || profile.increment_ms.is_some() {
+            let time_control = TimeControl::from_millis(
+                profile.initial_time_ms.unwrap_or(cfg.initial_time_ms),
+                profile.increment_ms.unwrap_or(cfg.increment_ms),
+            );
+            println!("      time control: {}", time_control);
+        }
+        if let Some(port) = profile.port {
+            println!("      port: {}", port);
+        }
+        if let Some(workers) = profile.workers {
+            println!("      workers: {}", workers);
+        }
+        if let Some(games) = profile.games {
+            println!("      games: {}", games);
+        }
+    }
+    println!("[+] To play a profile, try: \n\tstourney run --profile <name>");
+}
+
+/// Saves a profile to the global config file. Without a lineup or time control,
+/// the profile keeps the current selection and time control.
+/// Returns whether the profile was valid
+pub fn profiles_save_command(name: &str, mut profile: config::Profile) -> bool {
+    if !competitors::valid_alias(name) {
+        error!("[-] Invalid profile name {}", name);
+        error!("[-] Use only letters, digits, '-', '_', '.' and '@'");
+        return false;
+    }
+    let current = config::get_config();
+    let lineup = profile.lineup.take().unwrap_or(current.selected_projects);
+    let mut selected = Vec::new();
+    // Projects are stored by alias, or by full path if they are not named
+    for name in &lineup {
+        let Some(competitor) = competitors::resolve(name) else {
+            return false;
+        };
+        let known = current
+            .competitors
+            .iter()
+            .any(|c| c.alias == competitor.alias);
+        selected.push(match known {
+            true => competitor.alias,
+            false => competitor.spec().to_string(),
+        });
+    }
+    profile.lineup = Some(selected);
+    if profile.time_control.is_none() {
+        profile.initial_time_ms = Some(current.initial_time_ms);
+        profile.increment_ms = Some(current.increment_ms);
+    }
+    if let Err(e) = profile.validate() {
+        error!("[-] {}", e);
+        return false;
+    }
+
+    let mut cfg = config::global_config();
+    cfg.profiles.insert(name.to_string(), profile);
+    config::save_config(cfg);
+    println!("[+] Saved profile {}", name);
+    println!("[+] To play it, try: \n\tstourney run --profile {}", name);
+    true
+}
+
+/// Deletes a profile from the global config file, returning whether it existed
+pub fn profiles_delete_command(name: &str) -> bool {
+    let mut cfg = config::global_config();
+    if cfg.profiles.remove(name).is_none() {
+        match config::current_workspace() {
+            Some(workspace) if workspace.profiles.contains_key(name) => {
+                error!(
+                    "[-] Profile {} is defined in {}, edit it there instead",
+                    name,
+                    workspace.path.display()
+                );
+            }
+            _ => error!("[-] No profile named {}", name),
+        }
+        return false;
+    }
+    config::save_config(cfg);
+    println!("[+] Deleted profile {}", name);
+    true
+}
+
+/// Warns that a setting saved to the global config file has no effect
+/// here, because the workspace config file sets it too
+fn warn_if_overridden(key: &str) {
+    let Some(workspace) = config::current_workspace() else {
+        return;
+    };
+    let global = config::get_setting(&config::global_config(), key);
+    if global.is_ok() && config::get_setting(&config::get_config(), key) != global {
+        println!(
+            "[-] Note that {} overrides {} in this directory",
+            workspace.path.display(),
+            key
+        );
+    }
+}
+
+/// Selects the given competitors, by alias or project path, without any
+/// prompts. Projects that are not named yet are added as competitors.
+/// Returns whether every competitor was valid
+pub fn config_lineup_command(lineup: &[String]) -> bool {
+    if !(2..=4).contains(&lineup.len()) {
+        error!("[-] A lineup needs between 2 and 4 competitors");
+        return false;
+    }
+    let mut cfg = config::global_config();
+    let mut selected: Vec<String> = Vec::new();
+    for name in lineup {
+        let alias = if cfg.competitors.iter().any(|c| c.alias == *name) {
+            name.clone()
+        } else {
+            let spec = competitors::Spec::parse(name);
+            if !spec.check(true) {
+                error!("[-] No competitor or project named {}", name);
+                return false;
+            }
+            let spec = spec.full();
+            let alias = config::register(&mut cfg, &spec);
+            if !cfg.recents.contains(&spec.path) {
+                cfg.recents.insert(0, spec.path);
+            }
+            alias
+        };
+        if selected.contains(&alias) {
+            error!("[-] {} appears more than once in the lineup", alias);
+            return false;
+        }
+        selected.push(alias);
+    }
+    cfg.selected_projects = selected;
+    config::save_config(cfg);
+    config::display_competitors();
+    warn_if_overridden("lineup");
+    true
+}
+
+/// Settings given on the command line that override the
+/// configured settings of the arena
+#[derive(Debug, Default, Clone)]
+pub struct ArenaOptions {
+    /// The name of a preset time control
+    pub time_control: Option<String>,
+    /// The time each player starts a game with, in seconds
+    pub initial_time: Option<f64>,
+    /// The time added to a player's clock after each of their moves, in seconds
+    pub increment: Option<f64>,
+    /// The port to serve the arena on
+    pub port: Option<u16>,
+    /// The seed of the game, or of the first game when playing several
+    pub seed: Option<u64>,
+    /// Whether to play without the frontend, printing a single line per game
+    /// and writing the results to disk
+    pub headless: bool,
+    /// The number of arenas to run at once when playing a batch of games
+    pub workers: Option<usize>,
+    /// The file to export the results of every game to
+    pub results_out: Option<String>,
+    /// The format of the exported results, guessed from the file if not given
+    pub results_format: Option<String>,
+    /// The competitor or project whose frontend to serve, instead of the configured one
+    pub frontend: Option<String>,
+    /// Whether to play without the competitors that fail to build, instead of not playing
+    pub skip_broken: bool,
+}
+
+impl ArenaOptions {
+    /// Returns the configured time control with these options applied on top,
+    /// an explicit initial time or increment takes precedence over a preset
+    fn time_control(&self, cfg: &config::ProjectConfig) -> Result<TimeControl, ()> {
+        let mut time_control = TimeControl::from_millis(cfg.initial_time_ms, cfg.increment_ms);
+        if let Some(name) = &self.time_control {
+            time_control = match TimeControl::preset(name) {
+                Some(preset) => preset,
+                None => {
+                    error!("[-] Unknown time control {}", name);
+                    error!("[-] Expected one of {}", TimeControl::PRESETS.join(", "));
+                    return Err(());
+                }
+            };
+        }
+        if let Some(seconds) = self.initial_time {
+            time_control.initial_time = seconds_to_duration(seconds)?;
+        }
+        if let Some(seconds) = self.increment {
+            time_control.increment = seconds_to_duration(seconds)?;
+        }
+        if time_control.initial_time.is_zero() {
+            error!("[-] The initial time must be greater than zero");
+            return Err(());
+        }
+        Ok(time_control)
+    }
+
+    /// Returns the port to serve the arena on. A port given on the command line
+    /// must be available, while the configured port falls back to the next
+    /// available port if it is taken
+    fn port(&self, cfg: &config::ProjectConfig) -> Result<u16, ()> {
+        let port = self.port.unwrap_or(cfg.port);
+        if utils::port_available(port) {
+            return Ok(port);
+        }
+
+        let owner = match utils::port_owner(port) {
+            Some(owner) => format!("by {}", owner),
+            None => "by another process".to_string(),
+        };
+        if self.port.is_some() {
+            error!("[-] Port {} is already in use {}", port, owner);
+            error!("[-] Stop that process or choose another port with --port");
+            return Err(());
+        }
+        match utils::next_available_port(port) {
+            Some(available) => {
+                println!(
+                    "[+] Port {} is already in use {}, using port {} instead",
+                    port, owner, available
+                );
+                Ok(available)
+            }
+            None => {
+                error!("[-] Port {} is already in use {}", port, owner);
+                error!("[-] No available port found after {}", port);
+                Err(())
+            }
+        }
+    }
+
+    /// Creates the file to export results to, if one was asked for.
+    /// Returns an error if the file cannot be created
+    fn results_export(&self) -> Result<Option<results::ResultsExport>, ()> {
+        match &self.results_out {
+            Some(path) => results::ResultsExport::create(path, self.results_format.as_deref())
+                .map(Some)
+                .ok_or(()),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the number of arenas to run at once, one per CPU
+    /// unless configured or given on the command line
+    fn workers(&self, cfg: &config::ProjectConfig) -> Result<usize, ()> {
+        let workers = match self.workers.unwrap_or(cfg.workers) {
+            0 if self.workers.is_some() => {
+                error!("[-] The number of workers must be greater than zero");
+                return Err(());
+            }
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            workers => workers,
+        };
+        Ok(workers)
+    }
+}
+
+/// Returns `count` distinct available ports, starting from `first`
+fn available_ports(first: u16, count: usize) -> Result<Vec<u16>, ()> {
+    let mut ports = vec![first];
+    while ports.len() < count {
+        let last = ports[ports.len() - 1];
+        match utils::next_available_port(last) {
+            Some(port) => ports.push(port),
+            None => {
+                error!("[-] Not enough available ports for {} arenas", count);
+                return Err(());
+            }
+        }
+    }
+    Ok(ports)
+}
+
+/// Converts a number of seconds given by the user into a duration
+fn seconds_to_duration(seconds: f64) -> Result<Duration, ()> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        error!("[-] Invalid number of seconds: {}", seconds);
+    })
+}
+
+/// Builds the selected competitors and gathers everything needed to
+/// launch an arena with them, in the order they were selected
+fn setup_game(options: &ArenaOptions) -> Result<GameSetup, ()> {
     let cfg = config::get_config();
     if cfg.selected_projects.is_empty() {
         println!("No competitors selected yet!");
         println!("try running \n\tstourney config edit\nto add some competitors!");
         return Err(());
     }
+    let competitors = resolve_competitors(&cfg.selected_projects)?;
+    setup_game_with(&competitors, options)
+}
 
-    println!("[+] Running the tournament...");
+/// Finds the named competitors or projects a user asked for. Every game is
+/// rated, so a competitor can only take one seat: its ratings and history
+/// are kept under its alias
+fn resolve_competitors(names: &[String]) -> Result<Vec<Competitor>, ()> {
+    let mut resolved: Vec<Competitor> = Vec::new();
+    for name in names {
+        let competitor = competitors::resolve(name).ok_or(())?;
+        if !competitor.spec().check(true) {
+            return Err(());
+        }
+        if resolved.iter().any(|c| c.alias == competitor.alias) {
+            error!("[-] {} is playing more than once", competitor.alias);
+            println!(
+                "[-] To play a project against itself, add it again under another alias with\n\tstourney config add {} --alias <alias>",
+                competitor.spec()
+            );
+            return Err(());
+        }
+        resolved.push(competitor);
+    }
+    Ok(resolved)
+}
+
+/// Builds the given competitors and gathers everything needed to
+/// launch an arena with them, in the given order
+fn setup_game_with(competitors: &[Competitor], options: &ArenaOptions) -> Result<GameSetup, ()> {
+    let cfg = config::get_config();
+    let time_control = options.time_control(&cfg)?;
+    let port = options.port(&cfg)?;
+    let seed = options.seed.unwrap_or_else(seeds::random_seed);
     let mut binaries = Vec::new();
-    let port: u16 = 3030;
-    let initial_time = Duration::from_secs(10);
-    let increment = Duration::from_secs(1);
     let mut interpreter = None;
-    let mut static_files = None;
-
-    for competitor in cfg.selected_projects {
-        let project_type = utils::guess_project_type(&competitor);
-        match project_type {
-            utils::ProjectType::Rust | utils::ProjectType::Python => {
-                interpreter = Some(utils::python_interpreter_path(&competitor));
-            }
-            utils::ProjectType::Unknown => {
-                error!("[-] Unknown project type for {}", competitor);
-                error!("[-] Expected a Rust or Python project");
-                println!("[-] Exiting...");
-                return Err(());
+    let mut directories = Vec::new();
+
+    let mut aliases = Vec::new();
+    let mut projects = Vec::new();
+
+    let mut failures = Vec::new();
+
+    // Worktrees are checked out one at a time, as they share a repository
+    let mut checkouts = Vec::new();
+    for competitor in competitors {
+        let directory = competitor.spec().checkout().ok_or(())?;
+        let project_type = competitor.project_type(&directory);
+        checkouts.push((competitor, directory, project_type));
+    }
+    let builds = build_projects(&checkouts);
+
+    for (competitor, directory, project_type) in checkouts {
+        let spec = competitor.spec();
+        let built = &builds[&(directory.clone(), project_type)];
+        let launcher = match built
+            .clone()
+            .and_then(|()| competitor_launcher(competitor, &directory, project_type))
+        {
+            Ok(launcher) => launcher,
+            Err(summary) => {
+                failures.push((competitor, summary));
+                continue;
             }
+        };
+        if project_type == utils::ProjectType::Python {
+            let venv = competitors::interpreter_directory(&spec, &directory);
+            interpreter.get_or_insert(utils::python_interpreter_path(&venv));
         }
+        binaries.push(launcher);
+        aliases.push(competitor.alias.clone());
+        projects.push(spec.to_string());
+        directories.push(directory);
+    }
 
-        match project_type {
-            utils::ProjectType::Rust => {
-                utils::build_rust_project(&competitor);
-                binaries.push(utils::rust_binary_path(&competitor));
-            }
-            utils::ProjectType::Python => {
-                binaries.push(utils::python_binary_path(&competitor));
+    if !failures.is_empty() {
+        error!(
+            "[-] {} of {} competitors failed to build",
+            failures.len(),
+            competitors.len()
+        );
+        for (competitor, summary) in &failures {
+            error!("[-] {}:", competitor);
+            for line in summary.lines() {
+                eprintln!("      {}", line);
             }
-            _ => {}
         }
-
-        static_files = Some(utils::static_files_path(&competitor));
+        if !options.skip_broken {
+            println!("[-] Exiting... pass --skip-broken to play without them");
+            return Err(());
+        }
+        if aliases.len() < 2 {
+            error!("[-] Too few competitors left to play a game");
+            return Err(());
+        }
+        let dropped: Vec<&str> = failures.iter().map(|(c, _)| c.alias.as_str()).collect();
+        println!("[+] Playing without {}", dropped.join(", "));
     }
-    info!("Launching the arena...");
+    let static_files = match options.headless {
+        true => None,
+        false => Some(frontend(&aliases, &directories, options, &cfg)?),
+    };
     trace!("Port: {}", port);
-    trace!("Initial time: {:?}", initial_time);
-    trace!("Increment: {:?}", increment);
+    trace!("Time control: {}", time_control);
+    trace!("Seed: {}", seed);
     trace!("Interpreter: {:?}", interpreter);
     trace!("Static files: {:?}", static_files);
     trace!("Binaries: {:?}", binaries);
 
-    let arena = ArenaBuilder::new()
-        .port(port)
-        .binaries(binaries)
-        .initial_time(initial_time)
-        .increment(increment)
-        .python_interpreter(&interpreter.unwrap())
-        .static_files(&static_files.unwrap());
-    Ok(arena)
+    Ok(GameSetup {
+        competitors: aliases,
+        projects,
+        binaries,
+        // Python competitors are launched with their own interpreter,
+        // this one only runs python entry points of manifests
+        interpreter: interpreter.unwrap_or_else(|| "python3".to_string()),
+        static_files,
+        port,
+        time_control,
+        seed,
+        web: false,
+        record: None,
+    })
+}
+
+/// Builds every project checked out for the given competitors at once, each
+/// only once however many competitors play it, and returns whether each
+/// project was built, or a summary of why it was not
+fn build_projects(
+    checkouts: &[(&Competitor, String, utils::ProjectType)],
+) -> HashMap<(String, utils::ProjectType), Result<(), String>> {
+    let mut projects: Vec<(String, utils::ProjectType)> = Vec::new();
+    for (_, directory, project_type) in checkouts {
+        let project = (directory.clone(), *project_type);
+        if !projects.contains(&project) {
+            projects.push(project);
+        }
+    }
+    std::thread::scope(|scope| {
+        let builds: Vec<_> = projects
+            .into_iter()
+            .map(|(directory, project_type)| {
+                let build = {
+                    let directory = directory.clone();
+                    scope.spawn(move || build_project(&directory, project_type))
+                };
+                ((directory, project_type), build)
+            })
+            .collect();
+        builds
+            .into_iter()
+            .map(|(project, build)| {
+                let built = build
+                    .join()
+                    .unwrap_or_else(|_| Err("The build panicked".to_string()));
+                (project, built)
+            })
+            .collect()
+    })
+}
+
+/// Builds the project in `directory`, unless it is unchanged since it was
+/// last built, or returns a summary of why it could not be built
+fn build_project(directory: &str, project_type: utils::ProjectType) -> Result<(), String> {
+    match project_type {
+        utils::ProjectType::Rust => builds::build_rust(directory),
+        utils::ProjectType::Python => builds::build_python(directory),
+        utils::ProjectType::Manifest => ProjectManifest::load(directory)
+            .map_err(|e| format!("Invalid {}: {}", constants::MANIFEST_FILE_NAME, e))?
+            .build(directory),
+        utils::ProjectType::Unknown => Err(format!(
+            "Unknown project type, expected a Rust or Python project, or a {}",
+            constants::MANIFEST_FILE_NAME
+        )),
+    }
+}
+
+/// Returns the program the arena should launch for a competitor whose project
+/// was built in `directory`, or a summary of why it cannot be played
+fn competitor_launcher(
+    competitor: &Competitor,
+    directory: &str,
+    project_type: utils::ProjectType,
+) -> Result<String, String> {
+    let launcher = match project_type {
+        utils::ProjectType::Rust => {
+            let binary = utils::rust_binary_path(directory).ok_or_else(|| {
+                "The build succeeded but did not produce target/release/rust_client".to_string()
+            })?;
+            competitor.launcher(&binary)
+        }
+        utils::ProjectType::Python => {
+            // Every python competitor runs with its own virtual environment
+            let venv = competitors::interpreter_directory(&competitor.spec(), directory);
+            let interpreter = utils::python_interpreter_path(&venv);
+            let binary = utils::python_binary_path(directory);
+            competitor.python_launcher_with(&interpreter, &binary)
+        }
+        utils::ProjectType::Manifest => {
+            let manifest = ProjectManifest::load(directory)
+                .map_err(|e| format!("Invalid {}: {}", constants::MANIFEST_FILE_NAME, e))?;
+            let entry_point = manifest.entry_point(directory);
+            if manifest.run.is_none() && !entry_point.exists() {
+                return Err(format!(
+                    "The entry point {} does not exist",
+                    entry_point.display()
+                ));
+            }
+            competitor.manifest_launcher(&manifest, directory)
+        }
+        utils::ProjectType::Unknown => None,
+    };
+    launcher.ok_or_else(|| "Failed to write the launcher".to_string())
+}
+
+/// Returns the frontend for the arena to serve: the one chosen on the command
+/// line or in the config file, or else the first competitor's that has one,
+/// or else the frontend of stourney's own copy of the scaffolding
+fn frontend(
+    aliases: &[String],
+    directories: &[String],
+    options: &ArenaOptions,
+    cfg: &config::ProjectConfig,
+) -> Result<String, ()> {
+    let choice = options
+        .frontend
+        .clone()
+        .or_else(|| (!cfg.frontend.is_empty()).then(|| cfg.frontend.clone()));
+    let directory = match choice.as_deref() {
+        None => directories.iter().find(|d| utils::has_frontend(d)).cloned(),
+        Some(constants::MANAGED_FRONTEND) => None,
+        Some(name) => {
+            let directory = match aliases.iter().position(|alias| alias == name) {
+                Some(seat) => directories[seat].clone(),
+                None => {
+                    let competitor = competitors::resolve(name).ok_or(())?;
+                    competitor.spec().checkout().ok_or(())?
+                }
+            };
+            if !utils::has_frontend(&directory) {
+                error!("[-] {} has no frontend to serve", name);
+                error!(
+                    "[-] Choose another frontend, or `{}` for stourney's own",
+                    constants::MANAGED_FRONTEND
+                );
+                return Err(());
+            }
+            Some(directory)
+        }
+    };
+    match directory {
+        Some(directory) => Ok(utils::static_files_path(&directory)),
+        None => utils::managed_frontend().ok_or(()),
+    }
+}
+
+/// Sets up the initial arena with configurable settings
+fn setup_arena(options: &ArenaOptions) -> Result<GameSetup, ()> {
+    let setup = setup_game(options)?;
+    println!("[+] Running the tournament...");
+    info!("Launching the arena...");
+    Ok(setup)
+}
+
+/// Serves the arena until the user exits, recording the result of the
+/// game in the ratings ledger as soon as it is over. Returns whether
+/// the arena could be launched
+async fn launch_arena(setup: GameSetup, export: Option<results::ResultsExport>) -> bool {
+    println!("[+] Seed: {}", setup.seed);
+    seeds::record(&setup);
+    let mut child = match setup.spawn(Stdio::inherit()) {
+        Ok(child) => child,
+        Err(e) => {
+            error!("[-] Failed to launch the arena: {}", e);
+            return false;
+        }
+    };
+
+    let started = Instant::now();
+    let record = setup.record_file();
+    let mut server = tokio::task::spawn_blocking(move || child.wait());
+    tokio::select! {
+        _ = &mut server => return true,
+        outcome = game::watch_for_result(&record) => {
+            let duration = started.elapsed();
+            let report = results::GameReport::new(&setup, &outcome, duration);
+            history::record(&report);
+            if let Some(mut export) = export {
+                export.add(1, &report);
+                export.finish();
+            }
+            match outcome {
+                Ok(result) => {
+                    ratings::record_game(&setup.competitors, &result);
+                    if let Some(id) = replays::save(&setup, &result, duration) {
+                        println!("[+] Replay saved, to see it try: \n\tstourney replays show {}", id);
+                    }
+                    println!("[+] Game over! To see the updated ratings, try: \n\tstourney ratings");
+                }
+                Err(e) => error!("[-] The game failed: {}", e),
+            }
+        }
+    }
+    let _ = server.await;
+    true
 }
+
 /// Guides a user through running a competition
-pub async fn run_command() {
-    if let Ok(arena) = setup_arena() {
-        let arena = arena.build();
-        arena.launch().await;
+pub async fn run_command(options: &ArenaOptions) -> bool {
+    let Ok(export) = options.results_export() else {
+        return false;
+    };
+    let Ok(setup) = setup_arena(options) else {
+        return false;
+    };
+    launch_arena(setup, export).await
+}
+
+/// Plays every combination of `players` selected competitors against
+/// each other `games` times and prints the final standings
+pub fn tournament_command(
+    players: usize,
+    games: Option<usize>,
+    seating: &str,
+    options: &ArenaOptions,
+) -> bool {
+    if !(2..=4).contains(&players) {
+        println!("[-] A game must have between 2 and 4 players");
+        return false;
+    }
+    let competitors = config::get_config().selected_projects;
+    if !competitors.is_empty() && competitors.len() < players {
+        println!(
+            "[-] Not enough competitors for {}-player games, {} selected",
+            players,
+            competitors.len()
+        );
+        println!("try running \n\tstourney config edit\nto add some competitors!");
+        return false;
+    }
+    play_schedule(Some(players), games, seating, options)
+}
+
+/// Plays all of the selected competitors against each other in every
+/// seat order `games` times and prints the final standings
+pub fn match_command(games: Option<usize>, seating: &str, options: &ArenaOptions) -> bool {
+    let competitors = config::get_config().selected_projects;
+    if competitors.len() == 1 || competitors.len() > 4 {
+        println!(
+            "[-] A match needs between 2 and 4 competitors, {} selected",
+            competitors.len()
+        );
+        println!("try running \n\tstourney config edit\nto change the competitors!");
+        return false;
+    }
+    play_schedule(None, games, seating, options)
+}
+
+/// Plays the selected competitors against each other `games` times, or the
+/// configured number of games, without the frontend, in the order they were selected
+pub fn headless_command(games: Option<usize>, options: &ArenaOptions) -> bool {
+    match_command(games, "fixed", options)
+}
+
+/// Plays games in one arena per port at once. Each worker takes the next game
+/// from `game`, by number, until it returns `None`, and every finished game is
+/// passed to `finished` on this thread. Once `finished` returns false, no more
+/// games are started and the games still being played are waited for
+fn play_parallel<G, F>(ports: Vec<u16>, game: G, mut finished: F)
+where
+    G: Fn(usize) -> Option<GameSetup> + Sync,
+    F: FnMut(usize, GameSetup, Result<GameResult, GameError>, Duration) -> bool,
+{
+    let next = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for port in ports {
+            let (next, stopped, game, sender) = (&next, &stopped, &game, sender.clone());
+            scope.spawn(move || {
+                // Each worker waits for its game to be handled before starting
+                // another, so no game starts after `finished` asks to stop
+                let (reply, replies) = mpsc::channel();
+                while !stopped.load(Ordering::Relaxed) {
+                    let number = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(mut game) = game(number) else {
+                        break;
+                    };
+                    game.port = port;
+                    trace!("Game {} on port {}: {:?}", number, port, game.competitors);
+                    seeds::record(&game);
+
+                    let started = Instant::now();
+                    let outcome = game.play();
+                    let finished = (number, game, outcome, started.elapsed(), reply.clone());
+                    if sender.send(finished).is_err() || replies.recv().is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for (number, game, outcome, duration, reply) in receiver {
+            if !finished(number, game, outcome, duration) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+            let _ = reply.send(());
+        }
+    });
+}
+
+/// Records a finished game in the match history, and if it was completed,
+/// in the ratings and as a replay, returning its report
+fn record_finished(
+    game: &GameSetup,
+    outcome: &Result<GameResult, GameError>,
+    duration: Duration,
+) -> results::GameReport {
+    let report = results::GameReport::new(game, outcome, duration);
+    history::record(&report);
+    if let Ok(result) = outcome {
+        ratings::record_game(&game.competitors, result);
+        replays::save(game, result, duration);
+    }
+    report
+}
+
+/// Plays every scheduled game of `players` selected competitors, or of all
+/// of them if not given, running several arenas at once, then prints the
+/// final standings
+fn play_schedule(
+    players: Option<usize>,
+    games: Option<usize>,
+    seating: &str,
+    options: &ArenaOptions,
+) -> bool {
+    let games = games.unwrap_or_else(|| config::get_config().games.max(1));
+    let Some(seating) = tournament::Seating::from_name(seating) else {
+        error!("[-] Unknown seating {}", seating);
+        error!(
+            "[-] Expected one of {}",
+            tournament::Seating::NAMES.join(", ")
+        );
+        return false;
+    };
+    let Ok(workers) = options.workers(&config::get_config()) else {
+        return false;
+    };
+    let Ok(setup) = setup_game(options) else {
+        return false;
+    };
+    let competitors = &setup.competitors;
+    // Competitors that failed to build may have been dropped
+    let players = players.unwrap_or(competitors.len());
+    if competitors.len() < players {
+        error!(
+            "[-] Not enough competitors left for {}-player games",
+            players
+        );
+        return false;
+    }
+
+    let schedule = tournament::schedule(competitors.len(), players, games, seating);
+    let workers = workers.min(schedule.len()).max(1);
+    let Ok(ports) = available_ports(setup.port, workers) else {
+        return false;
+    };
+    let mut standings = tournament::Standings::new(competitors);
+    let Ok(mut export) = options.results_export() else {
+        return false;
+    };
+    let mut results = if options.headless {
+        let Some(results) = results::ResultsFile::create() else {
+            return false;
+        };
+        Some(results)
+    } else {
+        None
+    };
+
+    // Every game gets its own seed, derived from the first so that
+    // a whole batch can be reproduced from a single seed
+    let scheduled: Vec<GameSetup> = schedule
+        .iter()
+        .enumerate()
+        .map(|(number, lineup)| {
+            let mut game = setup.lineup(lineup);
+            game.seed = setup.seed.wrapping_add(number as u64);
+            game
+        })
+        .collect();
+    println!(
+        "[+] Running {} games at {} in {} arenas...",
+        schedule.len(),
+        setup.time_control,
+        workers
+    );
+
+    play_parallel(
+        ports,
+        |number| scheduled.get(number).cloned(),
+        |number, game, outcome, duration| {
+            let lineup = &schedule[number];
+            let report = record_finished(&game, &outcome, duration);
+            if let Some(export) = &mut export {
+                export.add(number + 1, &report);
+            }
+            if let Some(results) = &mut results {
+                println!("{}", report.summary(number + 1, schedule.len()));
+                results.write(&report);
+            } else {
+                println!(
+                    "[+] Game {}/{} (seed {})",
+                    number + 1,
+                    schedule.len(),
+                    game.seed
+                );
+                match &outcome {
+                    Ok(result) => {
+                        for (seat, &competitor) in lineup.iter().enumerate() {
+                            let marker = if result.winner == Some(seat) {
+                                "*"
+                            } else {
+                                " "
+                            };
+                            println!(
+                                "  {} {:>2} points  {}",
+                                marker, result.scores[seat], competitors[competitor]
+                            );
+                        }
+                    }
+                    Err(e) => error!("[-] Game {} failed: {}", number + 1, e),
+                }
+            }
+            match &outcome {
+                Ok(result) => standings.record(lineup, result),
+                Err(e) => standings.record_failure(lineup, e),
+            }
+            true
+        },
+    );
+
+    println!();
+    standings.display();
+    standings.display_significance();
+    if let Some(results) = results {
+        println!("[+] Results written to {}", results.path().display());
+    }
+    if let Some(export) = export {
+        export.finish();
+    }
+    println!("[+] To see the replays of these games, try: \n\tstourney replays list");
+    true
+}
+
+/// How a sequential probability ratio test between two projects ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtOutcome {
+    /// The candidate is at least `elo1` stronger than the base
+    Passed,
+    /// The candidate is no more than `elo0` stronger than the base
+    Failed,
+    /// The game limit was reached before the test could decide
+    Inconclusive,
+    /// The test could not be run, or was stopped by a game that failed
+    /// without a competitor to blame
+    Error,
+}
+
+/// Plays a base project against a candidate, swapping seats after every
+/// game, until a sequential probability ratio test decides whether the
+/// candidate is stronger, or `max_games` have been played
+pub fn sprt_command(
+    base: &str,
+    candidate: &str,
+    sprt: Sprt,
+    max_games: usize,
+    options: &ArenaOptions,
+) -> SprtOutcome {
+    let Ok(competitors) = resolve_competitors(&[base.to_string(), candidate.to_string()]) else {
+        return SprtOutcome::Error;
+    };
+    if sprt.elo0 >= sprt.elo1 {
+        error!("[-] elo0 must be lower than elo1");
+        return SprtOutcome::Error;
+    }
+    let valid = |probability: f64| probability > 0.0 && probability < 0.5;
+    if !valid(sprt.alpha) || !valid(sprt.beta) {
+        error!("[-] alpha and beta must be greater than 0 and less than 0.5");
+        return SprtOutcome::Error;
+    }
+    // Every game needs its pair with the seats swapped
+    let max_games = if max_games % 2 == 1 {
+        println!(
+            "[+] Rounding the game limit up to {}, so every game has its pair",
+            max_games + 1
+        );
+        max_games + 1
+    } else {
+        max_games
+    };
+    let Ok(workers) = options.workers(&config::get_config()) else {
+        return SprtOutcome::Error;
+    };
+    let Ok(mut export) = options.results_export() else {
+        return SprtOutcome::Error;
+    };
+    let headless = ArenaOptions {
+        headless: true,
+        ..options.clone()
+    };
+    let Ok(setup) = setup_game_with(&competitors, &headless) else {
+        return SprtOutcome::Error;
+    };
+    let Ok(ports) = available_ports(setup.port, workers.min(max_games).max(1)) else {
+        return SprtOutcome::Error;
+    };
+
+    let (lower, upper) = sprt.bounds();
+    println!(
+        "[+] Testing {} against {} at {}",
+        competitors[1].alias, competitors[0].alias, setup.time_control
+    );
+    println!(
+        "[+] H0: elo <= {}, H1: elo >= {}, alpha {}, beta {}, LLR bounds [{:.2}, {:.2}]",
+        sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta, lower, upper
+    );
+
+    // Counted from the candidate's side
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    let mut decision = None;
+    // Each pair of games swaps the seats, so moving first favours neither.
+    // The test only decides once every finished game has its pair, and
+    // then only lets the pairs that were started finish. This holds the
+    // number of games started and the number of games to start
+    let schedule = Mutex::new((0, max_games));
+    let mut unpaired = HashSet::new();
+    let mut aborted = false;
+    play_parallel(
+        ports,
+        |number| {
+            let mut schedule = schedule.lock().expect("[-] Failed to lock the schedule");
+            let (started, limit) = &mut *schedule;
+            if number >= *limit {
+                return None;
+            }
+            *started = (*started).max(number + 1);
+            let seats = if number % 2 == 0 { [1, 0] } else { [0, 1] };
+            let mut game = setup.lineup(&seats);
+            game.seed = setup.seed.wrapping_add(number as u64);
+            Some(game)
+        },
+        |number, game, outcome, duration| {
+            let report = record_finished(&game, &outcome, duration);
+            if let Some(export) = &mut export {
+                export.add(number + 1, &report);
+            }
+            if let Err(e) = &outcome {
+                println!("{}", report.summary(number + 1, max_games));
+                // A failure neither side caused says nothing about either,
+                // and leaving the game out would unbalance the seats
+                if decision.is_none() && e.seat().is_none() {
+                    aborted = true;
+                }
+            }
+            if aborted {
+                return false;
+            }
+            if !unpaired.remove(&(number / 2)) {
+                unpaired.insert(number / 2);
+            }
+            // The side that forfeits a game loses it
+            let winner = match &outcome {
+                Ok(result) => result.winner,
+                Err(e) => e.seat().map(|seat| 1 - seat),
+            };
+            let candidate = if number % 2 == 0 { 0 } else { 1 };
+            match winner {
+                Some(seat) if seat == candidate => wins += 1,
+                Some(_) => losses += 1,
+                None => draws += 1,
+            }
+            println!(
+                "[+] Game {:>4}  W {} D {} L {}  LLR {:>6.2}",
+                number + 1,
+                wins,
+                draws,
+                losses,
+                sprt.llr(wins, draws, losses)
+            );
+            // Games that were already running when the test decided are
+            // still recorded, but cannot change the decision
+            if decision.is_none() && unpaired.is_empty() {
+                decision = sprt.decide(wins, draws, losses);
+                if decision.is_some() {
+                    let mut schedule = schedule.lock().expect("[-] Failed to lock the schedule");
+                    let (started, limit) = &mut *schedule;
+                    *limit = (*limit).min(*started + *started % 2);
+                }
+            }
+            true
+        },
+    );
+
+    let played = wins + draws + losses;
+    let score = if played == 0 {
+        0.5
+    } else {
+        (wins as f64 + 0.5 * draws as f64) / played as f64
+    };
+    let (low, high) = stats::wilson_interval(wins as f64 + 0.5 * draws as f64, played);
+    println!();
+    println!(
+        "[+] {} games: {} wins, {} draws, {} losses, score {:.1}% ({:.1}-{:.1}%)",
+        played,
+        wins,
+        draws,
+        losses,
+        100.0 * score,
+        100.0 * low,
+        100.0 * high
+    );
+    if let Some(export) = export {
+        export.finish();
+    }
+    if aborted {
+        error!("[-] A game failed without either competitor to blame, so the test was stopped");
+        return SprtOutcome::Error;
+    }
+    match decision {
+        Some(SprtDecision::AcceptH1) => {
+            println!("[+] H1 accepted, the candidate is stronger");
+            SprtOutcome::Passed
+        }
+        Some(SprtDecision::AcceptH0) => {
+            println!("[-] H0 accepted, the candidate is not stronger");
+            SprtOutcome::Failed
+        }
+        None => {
+            println!(
+                "[-] No decision after {} games, raise --max-games to keep testing",
+                max_games
+            );
+            SprtOutcome::Inconclusive
+        }
+    }
+}
+
+/// Launches a single arena with the given setup, this is run in a
+/// separate process for every game
+pub async fn arena_command(setup: &str) {
+    let setup: GameSetup = match serde_json::from_str(setup) {
+        Ok(setup) => setup,
+        Err(e) => {
+            error!("[-] Invalid arena setup: {}", e);
+            return;
+        }
+    };
+    host::serve(setup).await;
+}
+
+/// Checks everything stourney needs to build and play the competitors and
+/// prints a checklist, with a suggested fix for every check that did not
+/// pass. Returns whether every check passed, warnings aside
+pub fn doctor_command() -> bool {
+    let sections = doctor::diagnose();
+    let mut failed = 0;
+    let mut warned = 0;
+    let mut total = 0;
+    for section in &sections {
+        println!("{}", section.title);
+        for check in &section.checks {
+            let marker = match check.status {
+                doctor::Status::Pass => "[+]",
+                doctor::Status::Warn => "[!]",
+                doctor::Status::Fail => "[-]",
+            };
+            let mut detail = check.detail.trim_end().lines();
+            println!(
+                "  {} {}: {}",
+                marker,
+                check.name,
+                detail.next().unwrap_or_default()
+            );
+            for line in detail {
+                println!("        {}", line);
+            }
+            if let Some(fix) = &check.fix {
+                println!("        fix: {}", fix);
+            }
+            total += 1;
+            match check.status {
+                doctor::Status::Pass => {}
+                doctor::Status::Warn => warned += 1,
+                doctor::Status::Fail => failed += 1,
+            }
+        }
+        println!();
     }
+    match (failed, warned) {
+        (0, 0) => println!("[+] All {} checks passed", total),
+        (0, _) => println!("[+] All {} checks passed, with {} warnings", total, warned),
+        _ => println!("[-] {} of {} checks failed", failed, total),
+    }
+    failed == 0
 }
 
 /// Attempts to update the stourney projects that exist in the recents list
@@ -159,13 +1321,263 @@ pub fn update_command() {
         println!("[+] Updating project: {}...", project);
         utils::update_scaffolding(&project);
     }
+    update_check::refresh_projects();
+    utils::update_managed_frontend();
 }
 
 /// Guides a user through running (and watching) a competition
-pub async fn watch_command() {
-    if let Ok(arena) = setup_arena() {
-        let arena = arena.send_to_web(true, &config::get_config().api_key);
-        let arena = arena.build();
-        arena.launch().await;
+pub async fn watch_command(options: &ArenaOptions) -> bool {
+    let Ok(export) = options.results_export() else {
+        return false;
+    };
+    let Ok(mut setup) = setup_arena(options) else {
+        return false;
+    };
+    setup.web = true;
+    launch_arena(setup, export).await
+}
+
+/// Lists every stored replay, oldest first
+pub fn replays_list_command() {
+    let ids = replays::ids();
+    println!("[+] Replays:");
+    if ids.is_empty() {
+        println!("No replays yet!");
+        println!("try running \n\tstourney run\nto play some games.");
+        return;
+    }
+    println!("  {:<32}  {:>8}  {:>5}  Scores", "Id", "Played", "Moves");
+    for id in ids {
+        match replays::load(&id) {
+            Ok(replay) => println!(
+                "  {:<32}  {:>8}  {:>5}  {}",
+                replay.id,
+                utils::format_age(replay.finished_at),
+                replay.move_count(),
+                replay.scoreline()
+            ),
+            Err(e) => warn!("[-] {}", e),
+        }
+    }
+}
+
+/// Prints every move of the replay with the given id, or unique id prefix
+pub fn replays_show_command(id: &str) {
+    let replay = match replays::load(id) {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("[-] {}", e);
+            println!("try running \n\tstourney replays list\nto see every replay.");
+            return;
+        }
+    };
+    println!("[+] Replay {}", replay.id);
+    println!("  Seed: {}", replay.seed);
+    println!("  Time control: {}", replay.time_control);
+    println!(
+        "  Played: {}, took {:.1}s",
+        utils::format_age(replay.finished_at),
+        replay.duration
+    );
+    println!("[+] Competitors:");
+    for (seat, competitor) in replay.competitors.iter().enumerate() {
+        let commit = competitor
+            .commit
+            .as_deref()
+            .unwrap_or("not a git repository");
+        println!(
+            "  {}. {}: {} ({})",
+            seat + 1,
+            replay.name(seat),
+            competitor.path,
+            commit
+        );
     }
+
+    println!("[+] Moves:");
+    for index in 0..replay.move_count() {
+        let (seat, description) = replay.describe(index);
+        let player = match seat {
+            Some(seat) => replay.name(seat),
+            None => "-".to_string(),
+        };
+        let timing = match replay.moves.get(index) {
+            Some(played) => format!("  ({:.2}s, {:.1}s left)", played.think_time, played.clock),
+            None => String::new(),
+        };
+        println!(
+            "  {:>4}. {:<16} {}{}",
+            index + 1,
+            player,
+            description,
+            timing
+        );
+    }
+
+    let winner = match replay.winner {
+        Some(seat) => replay.name(seat),
+        None => "draw".to_string(),
+    };
+    println!(
+        "[+] Final scores: {}  winner: {}",
+        replay.scoreline(),
+        winner
+    );
+}
+
+/// Steps through a replay in the terminal, given either the path
+/// of a replay file or the id of a stored replay
+pub fn replays_view_command(replay: &str) {
+    let path = Path::new(replay);
+    let loaded = if path.is_file() {
+        replays::load_file(path)
+    } else {
+        replays::load(replay)
+    };
+    let replay = match loaded {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("[-] {}", e);
+            return;
+        }
+    };
+    if let Err(e) = viewer::view(&replay) {
+        error!("[-] Failed to show the replay: {}", e);
+    }
+}
+
+/// Deletes every replay except the `keep` most recent ones
+pub fn replays_prune_command(keep: usize) {
+    let deleted = replays::prune(keep);
+    println!("[+] Deleted {} replays", deleted);
+}
+
+/// Filters for the games shown by `stourney history`
+#[derive(Debug, Default)]
+pub struct HistoryQuery {
+    /// Only games played by this project
+    pub project: Option<String>,
+    /// Only games also played by this project
+    pub against: Option<String>,
+    /// Only games finished within this long ago, such as `7d`
+    pub since: Option<String>,
+    /// The number of most recent games to list
+    pub limit: usize,
+}
+
+/// Lists the games in the match history that fit the query,
+/// followed by the record of each competitor in them
+pub fn history_command(query: &HistoryQuery) {
+    let since = match &query.since {
+        Some(since) => match history::parse_since(since) {
+            Some(seconds) => Some(seconds),
+            None => {
+                error!("[-] Invalid time span {}", since);
+                error!("[-] Expected a number followed by s, m, h, d or w, such as 7d");
+                return;
+            }
+        },
+        None => None,
+    };
+    let games = match history::games_since(history::cutoff(since)) {
+        Ok(games) => games,
+        Err(e) => {
+            error!("[-] Failed to read the match history: {}", e);
+            return;
+        }
+    };
+    let plays = |project: &Option<String>, game: &results::GameReport| match project {
+        Some(project) => history::seat_of(game, project).is_some(),
+        None => true,
+    };
+    let games: Vec<results::GameReport> = games
+        .into_iter()
+        .filter(|game| plays(&query.project, game) && plays(&query.against, game))
+        .collect();
+
+    println!("[+] Match history:");
+    if games.is_empty() {
+        println!("No games found!");
+        println!("try running \n\tstourney run\nto play some games.");
+        return;
+    }
+    let shown = games.len().saturating_sub(query.limit);
+    if shown > 0 {
+        println!("  ... {} earlier games", shown);
+    }
+    for game in &games[shown..] {
+        let lineup: Vec<String> = game
+            .competitors
+            .iter()
+            .enumerate()
+            .map(|(seat, competitor)| {
+                let marker = if game.winner == Some(seat) { "*" } else { "" };
+                match game.scores.get(seat) {
+                    Some(score) => {
+                        format!("{}{} {}", marker, utils::project_name(competitor), score)
+                    }
+                    None => utils::project_name(competitor),
+                }
+            })
+            .collect();
+        let outcome = match &game.failure {
+            Some(failure) => format!("  failed: {}", failure),
+            None => String::new(),
+        };
+        println!(
+            "  {:>8}  seed {:<20}  {}{}",
+            utils::format_age(game.finished_at),
+            game.seed,
+            lineup.join("  "),
+            outcome
+        );
+    }
+
+    // The record of every competitor, or only of the asked for project
+    let mut records: Vec<(String, history::Record)> = Vec::new();
+    for game in &games {
+        for (seat, competitor) in game.competitors.iter().enumerate() {
+            if let Some(project) = &query.project {
+                if !history::matches(competitor, project) {
+                    continue;
+                }
+            }
+            let index = match records.iter().position(|(c, _)| c == competitor) {
+                Some(index) => index,
+                None => {
+                    records.push((competitor.clone(), history::Record::default()));
+                    records.len() - 1
+                }
+            };
+            records[index].1.add(game, seat);
+        }
+    }
+    records.sort_by(|a, b| b.1.win_rate().total_cmp(&a.1.win_rate()));
+
+    println!();
+    match &query.against {
+        Some(against) => println!("[+] Record against {}:", against),
+        None => println!("[+] Record:"),
+    }
+    println!(
+        "  {:>5}  {:>4}  {:>5}  {:>6}  {:>6}  {:>8}  Competitor",
+        "Games", "Wins", "Draws", "Losses", "Failed", "Win rate"
+    );
+    for (competitor, record) in records {
+        println!(
+            "  {:>5}  {:>4}  {:>5}  {:>6}  {:>6}  {:>7.1}%  {}",
+            record.games,
+            record.wins,
+            record.draws,
+            record.losses,
+            record.failures,
+            100.0 * record.win_rate(),
+            competitor
+        );
+    }
+}
+
+/// Displays the rating of every competitor that has played a rated game
+pub fn ratings_command() {
+    ratings::display_ratings();
 }
@@ -0,0 +1,482 @@
+//! Contains utilities for scheduling the games of a round-robin
+//! tournament and tallying the standings as results come in
+use crate::game::{GameError, GameResult};
+use crate::stats;
+
+/// The largest number of players that can sit at a single game
+pub const MAX_PLAYERS: usize = 4;
+
+/// How the competitors of a lineup are seated across its games
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seating {
+    /// Always seat competitors in the order they were selected
+    Fixed,
+    /// Cycle the seats so every competitor moves first once
+    Rotate,
+    /// Play every possible seat order
+    Permute,
+}
+
+impl Seating {
+    /// The names of the seatings, as given on the command line
+    pub const NAMES: [&'static str; 3] = ["fixed", "rotate", "permute"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fixed" => Some(Seating::Fixed),
+            "rotate" => Some(Seating::Rotate),
+            "permute" => Some(Seating::Permute),
+            _ => None,
+        }
+    }
+
+    /// Returns every seat order of the given lineup that this seating plays
+    pub fn seat_orders(&self, lineup: &[usize]) -> Vec<Vec<usize>> {
+        match self {
+            Seating::Fixed => vec![lineup.to_vec()],
+            Seating::Rotate => (0..lineup.len())
+                .map(|shift| {
+                    let mut order = lineup.to_vec();
+                    order.rotate_left(shift);
+                    order
+                })
+                .collect(),
+            Seating::Permute => {
+                let mut orders = Vec::new();
+                permutations(&mut lineup.to_vec(), 0, &mut orders);
+                orders
+            }
+        }
+    }
+}
+
+/// Returns every lineup of `players` competitors out of `num_competitors`
+/// in every seat order of the given seating, each repeated `games` times,
+/// as indices into the list of competitors
+pub fn schedule(
+    num_competitors: usize,
+    players: usize,
+    games: usize,
+    seating: Seating,
+) -> Vec<Vec<usize>> {
+    let mut lineups = Vec::new();
+    combinations(num_competitors, players, 0, &mut Vec::new(), &mut lineups);
+
+    let mut scheduled = Vec::new();
+    for lineup in lineups {
+        // Repeat whole rounds of seat orders so that stopping
+        // early still leaves the seats close to balanced
+        for _ in 0..games {
+            scheduled.extend(seating.seat_orders(&lineup));
+        }
+    }
+    scheduled
+}
+
+/// Collects every ordering of `items` that keeps the items before `start` in place
+fn permutations(items: &mut Vec<usize>, start: usize, out: &mut Vec<Vec<usize>>) {
+    if start == items.len() {
+        out.push(items.clone());
+        return;
+    }
+    for i in start..items.len() {
+        items.swap(start, i);
+        permutations(items, start + 1, out);
+        items.swap(start, i);
+    }
+}
+
+/// Collects every increasing sequence of `size` indices below `n`
+/// that extends the given prefix
+fn combinations(
+    n: usize,
+    size: usize,
+    start: usize,
+    prefix: &mut Vec<usize>,
+    out: &mut Vec<Vec<usize>>,
+) {
+    if prefix.len() == size {
+        out.push(prefix.clone());
+        return;
+    }
+    for i in start..n {
+        prefix.push(i);
+        combinations(n, size, i + 1, prefix, out);
+        prefix.pop();
+    }
+}
+
+/// The record of a single competitor over the course of a tournament
+#[derive(Debug, Clone, Default)]
+pub struct Standing {
+    pub competitor: String,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// The games this competitor forfeited, which also count as losses
+    pub failures: usize,
+    /// The games that were forfeited, by this competitor or another, which
+    /// have no scores
+    pub unscored: usize,
+    /// The sum of the prestige points scored across all games
+    pub total_score: usize,
+    /// The sum of the squares of the prestige points scored in each game
+    pub total_squared_score: usize,
+    /// The number of games played in each seat
+    pub seat_games: [usize; MAX_PLAYERS],
+    /// The number of games won in each seat
+    pub seat_wins: [usize; MAX_PLAYERS],
+}
+
+impl Standing {
+    /// Tournament points, one for a win and a half for a draw
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + 0.5 * self.draws as f64
+    }
+
+    /// The number of games that finished with a score
+    fn scored(&self) -> usize {
+        self.games - self.unscored
+    }
+
+    /// The average prestige points scored per game that finished
+    pub fn average_score(&self) -> f64 {
+        if self.scored() == 0 {
+            return 0.0;
+        }
+        self.total_score as f64 / self.scored() as f64
+    }
+
+    /// Returns the win rate and the mean score with their 95% confidence
+    /// intervals, which are left out until there are enough games
+    fn intervals(&self) -> (String, String) {
+        let win_rate = if self.games == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.games as f64
+        };
+        let (mean, margin) = stats::mean_interval(
+            self.scored(),
+            self.total_score as f64,
+            self.total_squared_score as f64,
+        );
+        if self.scored() < stats::MIN_SAMPLE {
+            return (
+                format!("{:.1}% (n/a)", 100.0 * win_rate),
+                format!("{:.2} ± n/a", mean),
+            );
+        }
+        let (low, high) = stats::wilson_interval(self.wins as f64, self.games);
+        (
+            format!(
+                "{:.1}% ({:.1}-{:.1}%)",
+                100.0 * win_rate,
+                100.0 * low,
+                100.0 * high
+            ),
+            format!("{:.2} ± {:.2}", mean, margin),
+        )
+    }
+}
+
+/// The standings of every competitor in a tournament
+pub struct Standings {
+    standings: Vec<Standing>,
+    /// The games that failed without a competitor to blame,
+    /// which are left out of the standings
+    failed: usize,
+}
+
+impl Standings {
+    pub fn new(competitors: &[String]) -> Self {
+        let standings = competitors
+            .iter()
+            .map(|competitor| Standing {
+                competitor: competitor.clone(),
+                ..Default::default()
+            })
+            .collect();
+        Standings {
+            standings,
+            failed: 0,
+        }
+    }
+
+    /// Records the result of a game played by the given lineup,
+    /// where `lineup[seat]` is the competitor that played in that seat
+    pub fn record(&mut self, lineup: &[usize], result: &GameResult) {
+        for (seat, &competitor) in lineup.iter().enumerate() {
+            let standing = &mut self.standings[competitor];
+            standing.games += 1;
+            standing.seat_games[seat] += 1;
+            standing.total_score += result.scores[seat] as usize;
+            standing.total_squared_score += (result.scores[seat] as usize).pow(2);
+            match result.winner {
+                Some(winner) if winner == seat => {
+                    standing.wins += 1;
+                    standing.seat_wins[seat] += 1;
+                }
+                Some(_) => standing.losses += 1,
+                None => standing.draws += 1,
+            }
+        }
+    }
+
+    /// Records a game played by the given lineup that failed. The competitor
+    /// that caused it loses, and its opponent wins or, in games of more than
+    /// two players, every other competitor draws. Failures that cannot be put
+    /// down to a competitor are only counted
+    pub fn record_failure(&mut self, lineup: &[usize], error: &GameError) {
+        let Some(failed) = error.seat() else {
+            self.failed += 1;
+            return;
+        };
+        for (seat, &competitor) in lineup.iter().enumerate() {
+            let standing = &mut self.standings[competitor];
+            standing.games += 1;
+            standing.unscored += 1;
+            standing.seat_games[seat] += 1;
+            if seat == failed {
+                standing.losses += 1;
+                standing.failures += 1;
+            } else if lineup.len() == 2 {
+                standing.wins += 1;
+                standing.seat_wins[seat] += 1;
+            } else {
+                standing.draws += 1;
+            }
+        }
+    }
+
+    /// Returns the standings ordered from first to last place
+    pub fn ranked(&self) -> Vec<Standing> {
+        let mut ranked = self.standings.clone();
+        ranked.sort_by(|a, b| {
+            b.points()
+                .total_cmp(&a.points())
+                .then(b.average_score().total_cmp(&a.average_score()))
+        });
+        ranked
+    }
+
+    /// Prints the standings as a table
+    pub fn display(&self) {
+        println!("[+] Standings:");
+        println!(
+            "  {:>3}  {:>5}  {:>4}  {:>5}  {:>6}  {:>6}  {:>6}  {:>9}  Competitor",
+            "#", "Games", "Wins", "Draws", "Losses", "Failed", "Points", "Avg score"
+        );
+        for (place, standing) in self.ranked().iter().enumerate() {
+            println!(
+                "  {:>3}  {:>5}  {:>4}  {:>5}  {:>6}  {:>6}  {:>6.1}  {:>9.2}  {}",
+                place + 1,
+                standing.games,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.failures,
+                standing.points(),
+                standing.average_score(),
+                standing.competitor
+            );
+        }
+        if self.failed > 0 {
+            println!(
+                "[-] {} games failed without a competitor to blame and are not counted",
+                self.failed
+            );
+        }
+        self.display_seats();
+    }
+
+    /// Prints the win rate of every competitor in each seat they played,
+    /// followed by the win rate of each seat over all competitors
+    fn display_seats(&self) {
+        let seats = (0..MAX_PLAYERS)
+            .filter(|&seat| self.standings.iter().any(|s| s.seat_games[seat] > 0))
+            .count();
+        if seats == 0 {
+            return;
+        }
+
+        println!();
+        println!("[+] Win rate by seat:");
+        let header: String = (0..seats)
+            .map(|seat| format!("  {:>14}", format!("Seat {}", seat + 1)))
+            .collect();
+        println!("{}  Competitor", header);
+
+        let cell = |wins: usize, games: usize| {
+            if games == 0 {
+                return format!("  {:>14}", "-");
+            }
+            let rate = 100.0 * wins as f64 / games as f64;
+            format!("  {:>14}", format!("{}/{} {:.0}%", wins, games, rate))
+        };
+        for standing in self.ranked() {
+            let row: String = (0..seats)
+                .map(|seat| cell(standing.seat_wins[seat], standing.seat_games[seat]))
+                .collect();
+            println!("{}  {}", row, standing.competitor);
+        }
+
+        // Every game has exactly one player in each of its seats,
+        // so the games played in a seat is the number of games
+        let totals: String = (0..seats)
+            .map(|seat| {
+                let wins = self.standings.iter().map(|s| s.seat_wins[seat]).sum();
+                let games = self.standings.iter().map(|s| s.seat_games[seat]).sum();
+                cell(wins, games)
+            })
+            .collect();
+        println!("{}  (all competitors)", totals);
+    }
+
+    /// Prints the win rate and mean score of every competitor with their
+    /// 95% confidence intervals, and for two competitors, how likely it is
+    /// that the leader is really the stronger one
+    pub fn display_significance(&self) {
+        println!();
+        println!("[+] Confidence intervals (95%):");
+        println!(
+            "  {:>5}  {:>22}  {:>15}  Competitor",
+            "Games", "Win rate", "Mean score"
+        );
+        for standing in self.ranked() {
+            let (win_rate, mean_score) = standing.intervals();
+            println!(
+                "  {:>5}  {:>22}  {:>15}  {}",
+                standing.games, win_rate, mean_score, standing.competitor
+            );
+        }
+
+        let [leader, other] = &self.ranked()[..] else {
+            return;
+        };
+        // With two competitors every game is between the two of them,
+        // so the leader's losses are the other competitor's wins
+        let (wins, losses) = (leader.wins, leader.losses);
+        let p_value = stats::sign_test(wins, losses);
+        println!();
+        println!("[+] {} against {}:", leader.competitor, other.competitor);
+        println!("  {} wins, {} losses, {} draws", wins, losses, leader.draws);
+        println!(
+            "  Likelihood of superiority: {:.1}%",
+            100.0 * stats::likelihood_of_superiority(wins, losses)
+        );
+        let verdict = if p_value < stats::SIGNIFICANCE {
+            "the difference is significant at the 5% level"
+        } else {
+            "the difference could be noise, play more games to be sure"
+        };
+        println!("  Sign test p-value: {:.4}, {}", p_value, verdict);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combinations_are_sorted_and_distinct() {
+        let mut lineups = Vec::new();
+        combinations(4, 2, 0, &mut Vec::new(), &mut lineups);
+        assert_eq!(
+            lineups,
+            vec![
+                vec![0, 1],
+                vec![0, 2],
+                vec![0, 3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 3]
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_schedule_plays_every_lineup_once_per_game() {
+        assert_eq!(
+            schedule(3, 2, 1, Seating::Fixed),
+            vec![vec![0, 1], vec![0, 2], vec![1, 2]]
+        );
+        assert_eq!(schedule(4, 3, 2, Seating::Fixed).len(), 8);
+        assert_eq!(schedule(2, 2, 0, Seating::Fixed).len(), 0);
+    }
+
+    #[test]
+    fn rotated_schedule_repeats_whole_rounds() {
+        assert_eq!(
+            schedule(2, 2, 2, Seating::Rotate),
+            vec![vec![0, 1], vec![1, 0], vec![0, 1], vec![1, 0]]
+        );
+        assert_eq!(
+            Seating::Rotate.seat_orders(&[0, 1, 2]),
+            vec![vec![0, 1, 2], vec![1, 2, 0], vec![2, 0, 1]]
+        );
+    }
+
+    #[test]
+    fn permuted_schedule_plays_every_seat_order() {
+        let mut orders = Seating::Permute.seat_orders(&[0, 1, 2]);
+        assert_eq!(orders.len(), 6);
+        orders.sort();
+        orders.dedup();
+        assert_eq!(orders.len(), 6);
+        assert_eq!(schedule(4, 4, 1, Seating::Permute).len(), 24);
+    }
+
+    #[test]
+    fn intervals_need_enough_games() {
+        let standing = Standing {
+            games: 1,
+            wins: 1,
+            total_score: 15,
+            total_squared_score: 225,
+            ..Standing::default()
+        };
+        assert_eq!(
+            standing.intervals(),
+            ("100.0% (n/a)".to_string(), "15.00 ± n/a".to_string())
+        );
+
+        let standing = Standing {
+            games: 10,
+            wins: 5,
+            total_score: 100,
+            total_squared_score: 1000,
+            ..Standing::default()
+        };
+        assert_eq!(
+            standing.intervals(),
+            ("50.0% (23.7-76.3%)".to_string(), "10.00 ± 0.00".to_string())
+        );
+    }
+
+    #[test]
+    fn forfeits_are_losses_without_a_score() {
+        let mut standings = Standings::new(&["a".to_string(), "b".to_string(), "c".to_string()]);
+        standings.record_failure(&[0, 1], &GameError::Forfeited(1));
+        standings.record_failure(&[0, 1, 2], &GameError::Forfeited(0));
+        standings.record_failure(&[1, 2], &GameError::TimedOut);
+        let [a, b, c] = &standings.standings[..] else {
+            unreachable!();
+        };
+        assert_eq!((a.wins, a.draws, a.losses, a.failures), (1, 0, 1, 1));
+        assert_eq!((b.wins, b.draws, b.losses, b.failures), (0, 1, 1, 1));
+        assert_eq!((c.wins, c.draws, c.losses, c.failures), (0, 1, 0, 0));
+        assert_eq!(a.seat_wins[0], 1);
+        assert_eq!(a.average_score(), 0.0);
+        assert_eq!(standings.failed, 1);
+    }
+
+    #[test]
+    fn every_competitor_moves_first_equally_often_when_rotated() {
+        let mut first = [0; 4];
+        for lineup in schedule(4, 3, 1, Seating::Rotate) {
+            first[lineup[0]] += 1;
+        }
+        assert_eq!(first, [3, 3, 3, 3]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Contains the checks stourney makes when it starts, for a new version of
+//! stourney and for recent projects whose scaffolding is out of date. Both
+//! are slow, and the first needs the network, so their results are cached
+//! next to the config file and refreshed in the background once they are
+//! older than a day
+use crate::config;
+use crate::constants;
+use crate::results::unix_time;
+use crate::utils;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// How long the results of the checks are used for, in seconds
+const TTL: u64 = 24 * 60 * 60;
+
+/// How long to wait for a background refresh to finish before starting
+/// another one, in seconds
+const REFRESH_TIMEOUT: u64 = 10 * 60;
+
+/// The subcommand that refreshes the checks in the background
+pub const REFRESH_COMMAND: &str = "refresh-update-check";
+
+/// The results of the last checks
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UpdateCheck {
+    /// When the checks last finished, in seconds since the unix epoch
+    #[serde(default)]
+    checked_at: u64,
+    /// When a refresh was last started, in seconds since the unix epoch
+    #[serde(default)]
+    refreshing_since: u64,
+    /// The latest version of stourney, if it could be found
+    #[serde(default)]
+    latest_version: Option<String>,
+    /// The recent projects whose scaffolding is out of date
+    #[serde(default)]
+    out_of_date_projects: Vec<String>,
+}
+
+impl UpdateCheck {
+    fn path() -> PathBuf {
+        config::config_directory().join(constants::UPDATE_CHECK_FILE_NAME)
+    }
+
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(Self::path()) else {
+            return UpdateCheck::default();
+        };
+        serde_json::from_str(&contents).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let path = Self::path();
+        let contents = serde_json::to_string_pretty(self).expect("[-] Failed to serialize");
+        if let Err(e) = fs::write(&path, contents) {
+            warn!("[-] Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.checked_at) >= TTL
+            && now.saturating_sub(self.refreshing_since) >= REFRESH_TIMEOUT
+    }
+}
+
+/// Warns the user about what the last checks found, and starts refreshing
+/// them in the background if they are out of date. Never waits on the checks
+pub fn warn_if_out_of_date() {
+    let mut check = UpdateCheck::load();
+    if !check.out_of_date_projects.is_empty() {
+        eprintln!("Some projects are out of date, run `stourney update` to update them");
+    }
+    if matches!(&check.latest_version, Some(latest) if latest != constants::VERSION) {
+        eprintln!("WARNING: stourney is out of date! Run `cargo install stourney` to update!");
+    }
+
+    let now = unix_time();
+    if !check.is_stale(now) {
+        return;
+    }
+    let Ok(stourney) = std::env::current_exe() else {
+        warn!("[-] Failed to find the stourney binary to check for updates");
+        return;
+    };
+    // Saved first, so that it cannot overwrite the results of a quick refresh
+    check.refreshing_since = now;
+    check.save();
+    let refresh = Command::new(stourney)
+        .arg(REFRESH_COMMAND)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match refresh {
+        Ok(_) => info!("[+] Checking for updates in the background"),
+        Err(e) => warn!("[-] Failed to check for updates: {}", e),
+    }
+}
+
+/// Runs the checks and caches their results
+pub fn refresh() {
+    let check = UpdateCheck {
+        checked_at: unix_time(),
+        refreshing_since: 0,
+        latest_version: utils::latest_version(),
+        out_of_date_projects: utils::out_of_date_projects(),
+    };
+    check.save();
+}
+
+/// Checks again which recent projects are out of date, such as after
+/// updating them, keeping the cached latest version of stourney
+pub fn refresh_projects() {
+    let mut check = UpdateCheck::load();
+    check.out_of_date_projects = utils::out_of_date_projects();
+    check.save();
+}
@@ -1,12 +1,16 @@
+use crate::builds;
 use crate::config;
 use crate::constants::*;
 use crate::dialogue;
+use crate::doctor;
+use crate::manifest;
 use log::{error, info, trace, warn};
-use std::path::Path;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 /// Contains utilities for interacting with the file system and directories
 /// of different operating systems, as well as interacting with external
 /// binaries
-use std::process::Command;
+use std::process::{Command, ExitStatus};
 use std::{fs, io};
 
 /// Checks to see if git exists and is callable on this system
@@ -34,7 +38,7 @@ pub fn python3_found() -> Option<String> {
 
         trace!("[-] Python 3 not found, attempting to use python --version");
         let command_result = Command::new("python").arg("--version").output();
-        if !command_result.is_ok() {
+        if command_result.is_err() {
             return None;
         }
         // Check that Python 3.x in string
@@ -46,7 +50,7 @@ pub fn python3_found() -> Option<String> {
 
         trace!("[-] Python 3 not found, attempting to use py -3 --version");
         let command_result = Command::new("py").arg("-3").arg("--version").output();
-        if !command_result.is_ok() {
+        if command_result.is_err() {
             return None;
         }
 
@@ -68,7 +72,7 @@ pub fn python3_found() -> Option<String> {
             return Some("python3".to_string());
         }
         let command_result = Command::new("python").arg("--version").output();
-        if !command_result.is_ok() {
+        if command_result.is_err() {
             return None;
         }
         // Check that Python 3.x in string
@@ -110,7 +114,7 @@ pub fn python_venv_found(interpreter: &str) -> bool {
         return false;
     }
     trace!("[+] Python venv module found!");
-    return true;
+    true
 }
 
 /// Given a path to a string that is a python interpreter, check to see if
@@ -143,37 +147,25 @@ pub fn python_pip_found(interpreter: &str) -> bool {
         return false;
     }
     trace!("[+] Python pip module found!");
-    return true;
+    true
 }
 
+/// Checks that git, python 3 and python's pip and venv modules are installed,
+/// reporting how to install any that are missing
 pub fn prereqs_found() -> bool {
-    if !git_exists() {
-        error!("[-] Git not found, please install git and try again");
-        return false;
-    }
-    let python_interpreter = python3_found();
-    if python_interpreter.is_some() {
-        info!("[+] Python 3 found!");
-    } else {
-        error!("[-] Python 3 not found, please install python 3 and try again");
-        return false;
-    }
-    let python_interpreter = python_interpreter.unwrap();
-    if python_pip_found(&python_interpreter) {
-        info!("[+] Python pip found!");
-    } else {
-        error!("[-] Python pip not found, please install python pip and try again");
-        return false;
-    }
-
-    if python_venv_found(&python_interpreter) {
-        info!("[+] Python venv found!");
-    } else {
-        error!("[-] Python venv not found, please install python venv and try again");
-        return false;
+    let mut found = true;
+    for check in doctor::prerequisite_checks() {
+        if check.status == doctor::Status::Fail {
+            error!("[-] {} not found: {}", check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                error!("[-] {}", fix);
+            }
+            found = false;
+        } else {
+            info!("[+] {} found: {}", check.name, check.detail);
+        }
     }
-
-    return true;
+    found
 }
 
 /// Setup a new python virtual environment in the specified directory
@@ -184,7 +176,7 @@ pub fn setup_venv(directory: &str) -> bool {
         .arg("venv")
         .arg(directory)
         .output();
-    if !command_result.is_ok() {
+    if command_result.is_err() {
         error!("[-] Failed to create virtual environment in {}", directory);
         return false;
     }
@@ -195,7 +187,7 @@ pub fn setup_venv(directory: &str) -> bool {
         .arg("install")
         .arg("maturin[patchelf]")
         .output();
-    if !command_result.is_ok() {
+    if command_result.is_err() {
         error!("[-] Failed to install maturin[patchelf]");
         return false;
     }
@@ -273,19 +265,18 @@ pub fn copy_example(example: &Path, directory: &str) -> bool {
 
 /// Builds a maturin project in the partially initialized project directory
 /// so that FFI bindings for python can be installed to the python
-/// virtual environment
-fn maturin_build(directory: &str) {
-    // TODO: simple error handling
-
-    let old_path = std::env::var("PATH").expect("[-] Failed to get PATH variable");
+/// virtual environment. Returns a summary of the errors if either the
+/// build or the installation failed
+pub fn maturin_build(directory: &str) -> Result<(), String> {
+    let old_path = std::env::var("PATH").map_err(|e| format!("Failed to get PATH: {}", e))?;
 
     let virtual_env_binaries = if cfg!(target_os = "windows") {
         Path::new(directory).join("venv").join("Scripts")
     } else {
         Path::new(directory).join("venv").join("bin")
     };
-    let virtual_env_binaries = virtual_env_binaries.to_str().unwrap();
-    let virtual_env_binaries = relative_to_full_path(virtual_env_binaries);
+    let virtual_env_binaries = virtual_env_binaries.to_string_lossy();
+    let virtual_env_binaries = relative_to_full_path(&virtual_env_binaries);
 
     // We need to add the virtual environment binaries to the path
     // because maturin requires `bin/patchelf` to be in the path for linux systems
@@ -299,16 +290,33 @@ fn maturin_build(directory: &str) {
         .join("lib")
         .join("scaffolding")
         .join("python_ffi")
-        .join("Cargo.toml")
+        .join("Cargo.toml");
+    let ffi_cargo_toml = ffi_cargo_toml
         .canonicalize()
-        .unwrap();
-    let mut ffi_cargo_toml = ffi_cargo_toml.to_str().unwrap();
+        .map_err(|e| format!("Failed to find {}: {}", ffi_cargo_toml.display(), e))?;
+    let ffi_cargo_toml = ffi_cargo_toml.to_string_lossy();
+    let mut ffi_cargo_toml = ffi_cargo_toml.as_ref();
     if cfg!(target_os = "windows") && ffi_cargo_toml.starts_with("\\\\?\\") {
         //strip out \\?\ string for canonical
         ffi_cargo_toml = &ffi_cargo_toml[4..];
     }
 
-    let a = Command::new("maturin")
+    // Wheels of earlier builds would otherwise be installed if this one fails
+    let wheels = |directory: &str| -> Result<Vec<PathBuf>, String> {
+        let entries =
+            fs::read_dir(directory).map_err(|e| format!("Failed to read {}: {}", directory, e))?;
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "whl"))
+            .collect())
+    };
+    for wheel in wheels(&virtual_env_binaries)? {
+        fs::remove_file(&wheel)
+            .map_err(|e| format!("Failed to remove {}: {}", wheel.display(), e))?;
+    }
+
+    let output = Command::new("maturin")
         .arg("build")
         .arg("--release")
         .arg("--manifest-path")
@@ -319,50 +327,52 @@ fn maturin_build(directory: &str) {
         .arg("./python")
         .env("PATH", new_path)
         .current_dir(&virtual_env_binaries)
-        .spawn();
-
-    a.expect("[-] Failed to build maturin project")
-        .wait()
-        .expect("[-] Failed to wait for maturin project to build");
-
+        .output()
+        .map_err(|e| format!("Failed to run maturin: {}", e))?;
+    if !output.status.success() {
+        let errors = String::from_utf8_lossy(&output.stderr);
+        let errors = errors
+            .lines()
+            .filter(|line| line.to_lowercase().contains("error"));
+        return Err(summarize(errors, &output.status));
+    }
     info!("[+] Maturin project built successfully!");
 
-    // Walk the contents of the bin directory for a .whl file
-    let whl_file = fs::read_dir(&virtual_env_binaries)
-        .expect("[-] Failed to read directory contents")
-        .filter_map(|entry| entry.ok())
-        .find(|entry| entry.path().extension().map_or(false, |ext| ext == "whl"))
-        .expect("[-] Failed to find .whl file");
-
+    let Some(whl_file) = wheels(&virtual_env_binaries)?.pop() else {
+        return Err("Maturin did not produce a .whl file".to_string());
+    };
     let interpreter = python_interpreter_path(directory);
     info!("[+] Interpreter: {}", interpreter);
 
-    trace!("Found whl file: {:?}", whl_file.path());
-    let whl_file = whl_file.path();
-    let mut whl_file = whl_file.to_str().unwrap();
+    trace!("Found whl file: {:?}", whl_file);
+    let whl_file = whl_file.to_string_lossy();
+    let mut whl_file = whl_file.as_ref();
     if cfg!(target_os = "windows") && whl_file.starts_with("\\\\?\\") {
         whl_file = &whl_file[4..];
     }
 
-    let command = Command::new(&interpreter)
+    let output = Command::new(&interpreter)
         .arg("-m")
         .arg("pip")
         .arg("install")
-        .arg(&whl_file)
+        .arg(whl_file)
         .arg("--force-reinstall")
-        .spawn();
-    command
-        .expect("[-] Failed to install wheel file")
-        .wait()
-        .expect("[-] Failed to install wheel file");
+        .output()
+        .map_err(|e| format!("Failed to run pip: {}", e))?;
+    if !output.status.success() {
+        let errors = String::from_utf8_lossy(&output.stderr);
+        return Err(summarize(errors.lines(), &output.status));
+    }
 
     info!("[+] Wheel file installed successfully!");
+    Ok(())
 }
 
 /// Creates a new project in the specified empty directory
 /// - Initializes the stourney arena repository
 /// - Initializes the python virtual environment needed for the project
 /// - Initializes project template with given parameters
+///
 /// TODO: clean up .git?
 pub fn create_project(project_directory: &str) -> bool {
     let arena_lib = Path::new(&project_directory).join("lib");
@@ -377,23 +387,27 @@ pub fn create_project(project_directory: &str) -> bool {
     };
 
     println!("[+] Downloading and installing...");
-    if !clone_repo(&arena_lib, STOURNEY_ARENA_REPO_URL) {
+    if !clone_repo(arena_lib, STOURNEY_ARENA_REPO_URL) {
         return false;
     }
-    if !copy_example(&example, &project_directory) {
+    if !copy_example(&example, project_directory) {
         return false;
     }
     if !setup_venv(venv_dir) {
         return false;
     }
-    maturin_build(&project_directory);
+    if let Err(summary) = maturin_build(project_directory) {
+        error!("[-] Failed to build the python bindings:\n{}", summary);
+        return false;
+    }
+    builds::record(project_directory, builds::Step::Maturin);
     true
 }
 
 /// Check whether the given directory is likely to have
 /// been created by the command:
 ///
-/// ```no_run
+/// ```bash
 /// stourney new <directory>
 /// ```
 pub fn check_project(directory: &str, verbose: bool) -> bool {
@@ -409,6 +423,17 @@ pub fn check_project(directory: &str, verbose: bool) -> bool {
         }
         return false;
     }
+    // Projects with a manifest bring their own build and need no scaffolding
+    if manifest::has_manifest(directory) {
+        if let Err(e) = manifest::ProjectManifest::load(directory) {
+            if verbose {
+                error!("[-] Invalid {} in {}", MANIFEST_FILE_NAME, directory);
+                error!("[-] {}", e);
+            }
+            return false;
+        }
+        return true;
+    }
     if !Path::new(directory).join("lib").exists() {
         if verbose {
             error!(
@@ -448,11 +473,14 @@ pub fn check_project(directory: &str, verbose: bool) -> bool {
     if matches!(guess_project_type(directory), ProjectType::Unknown) {
         if verbose {
             error!("[-] Directory {} is invalid", directory);
-            error!("[-] Expected a Cargo.toml or bot.py file");
+            error!(
+                "[-] Expected a Cargo.toml, bot.py or {} file",
+                MANIFEST_FILE_NAME
+            );
         }
         return false;
     }
-    return true;
+    true
 }
 
 /// Convert a relative path to a full path
@@ -469,19 +497,33 @@ pub fn relative_to_full_path(relative_path: &str) -> String {
     full_path.to_string()
 }
 
+/// Returns a short name for a project, the name of its directory
+pub fn project_name(project_directory: &str) -> String {
+    match Path::new(project_directory).file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => project_directory.to_string(),
+    }
+}
+
 /// Convert a full path to a relative path
 pub fn full_to_relative_path(_full_path: &str) -> String {
     todo!()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ProjectType {
     Python,
     Rust,
+    /// A project in any language, built and run as its manifest declares
+    Manifest,
     Unknown,
 }
 
 /// Guess the project type based on the contents of the directory
 pub fn guess_project_type(directory: &str) -> ProjectType {
+    if manifest::has_manifest(directory) {
+        return ProjectType::Manifest;
+    }
     if Path::new(directory).join("bot.py").exists() {
         return ProjectType::Python;
     }
@@ -491,17 +533,41 @@ pub fn guess_project_type(directory: &str) -> ProjectType {
     ProjectType::Unknown
 }
 
-pub fn build_rust_project(project_directory: &str) {
-    let process = Command::new("cargo")
+/// Builds the rust project in the given directory in release mode.
+/// Returns a summary of the compiler errors if the build failed
+pub fn build_rust_project(project_directory: &str) -> Result<(), String> {
+    println!("[+] Building {}...", project_directory);
+    let output = Command::new("cargo")
         .arg("build")
         .arg("--release")
+        .arg("--message-format=short")
         .current_dir(project_directory)
-        .spawn();
-    let _ = process
-        .expect("[-] Failed to build rust project")
-        .wait()
-        .expect("[-] Failed to wait for rust project to build");
+        .output()
+        .map_err(|e| format!("Failed to run cargo: {}", e))?;
+    if !output.status.success() {
+        let errors = String::from_utf8_lossy(&output.stderr);
+        let errors = errors
+            .lines()
+            .filter(|line| line.starts_with("error") || line.contains(": error"));
+        return Err(summarize(errors, &output.status));
+    }
     info!("[+] Rust project built successfully!");
+    Ok(())
+}
+
+/// Summarizes the error lines of a failed command,
+/// keeping only the first few so that every failure fits on screen
+pub fn summarize<'a>(lines: impl Iterator<Item = &'a str>, status: &ExitStatus) -> String {
+    const SHOWN: usize = 10;
+    let lines: Vec<&str> = lines.map(str::trim_end).filter(|l| !l.is_empty()).collect();
+    if lines.is_empty() {
+        return format!("The build failed with {}", status);
+    }
+    let mut summary = lines[..lines.len().min(SHOWN)].join("\n");
+    if lines.len() > SHOWN {
+        summary += &format!("\n... and {} more", lines.len() - SHOWN);
+    }
+    summary
 }
 
 pub fn python_interpreter_path(project_directory: &str) -> String {
@@ -527,14 +593,69 @@ pub fn python_binary_path(project_directory: &str) -> String {
         .to_string()
 }
 
-pub fn rust_binary_path(project_directory: &str) -> String {
+/// Returns the full path to the binary of the rust project in the
+/// given directory, or `None` if it has not been built
+pub fn rust_binary_path(project_directory: &str) -> Option<String> {
     let binary_path = Path::new(project_directory)
         .join("target")
         .join("release")
         .join("rust_client");
-    let binary_path = binary_path.to_str().unwrap();
-    let binary_path = relative_to_full_path(binary_path);
-    binary_path
+    if !binary_path.is_file() {
+        return None;
+    }
+    Some(relative_to_full_path(binary_path.to_str()?))
+}
+
+/// Returns whether the project in the given directory has the
+/// scaffolding's frontend
+pub fn has_frontend(project_directory: &str) -> bool {
+    Path::new(project_directory)
+        .join("lib")
+        .join("scaffolding")
+        .join("frontend")
+        .is_dir()
+}
+
+/// Returns the frontend of stourney's own copy of the scaffolding,
+/// downloading it next to the config file the first time it is needed
+pub fn managed_frontend() -> Option<String> {
+    let scaffolding = config::config_directory().join(SCAFFOLDING_DIRECTORY);
+    let frontend = scaffolding.join("scaffolding").join("frontend");
+    if !frontend.is_dir() {
+        println!("[+] Downloading the frontend...");
+        let _ = fs::remove_dir_all(&scaffolding);
+        if !clone_repo(scaffolding.to_str()?, STOURNEY_ARENA_REPO_URL) || !frontend.is_dir() {
+            error!(
+                "[-] Failed to download the frontend to {}",
+                scaffolding.display()
+            );
+            return None;
+        }
+    }
+    Some(frontend.to_str()?.to_string())
+}
+
+/// Updates stourney's own copy of the scaffolding, if it has one
+pub fn update_managed_frontend() {
+    let scaffolding = config::config_directory().join(SCAFFOLDING_DIRECTORY);
+    if !scaffolding.exists() {
+        return;
+    }
+    let git_command = Command::new("git")
+        .arg("pull")
+        .arg("origin")
+        .arg("main")
+        .current_dir(&scaffolding)
+        .output();
+    match git_command {
+        Ok(output) if output.status.success() => {
+            info!("[+] Frontend updated successfully!")
+        }
+        _ => warn!(
+            "[-] Failed to update the frontend in {}",
+            scaffolding.display()
+        ),
+    }
 }
 
 pub fn static_files_path(project_directory: &str) -> String {
@@ -543,15 +664,106 @@ pub fn static_files_path(project_directory: &str) -> String {
         .join("scaffolding")
         .join("frontend");
     let static_files = static_files.to_str().unwrap();
-    let static_files = relative_to_full_path(static_files);
-    static_files
+    relative_to_full_path(static_files)
+}
+
+/// Returns true if nothing is listening on the given local port
+pub fn port_available(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// Returns the first available port after the given port
+pub fn next_available_port(port: u16) -> Option<u16> {
+    (port.checked_add(1)?..=u16::MAX).find(|&p| port_available(p))
+}
+
+/// Returns the name and process id of the process listening on the given port,
+/// if it can be determined
+#[cfg(target_os = "linux")]
+pub fn port_owner(port: u16) -> Option<String> {
+    // Find the inode of the listening socket, then the process holding it open
+    const LISTEN: &str = "0A";
+    let inode = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|table| fs::read_to_string(table).ok())
+        .flat_map(|table| {
+            table
+                .lines()
+                .skip(1)
+                .map(|line| line.split_whitespace().map(String::from).collect())
+                .collect::<Vec<Vec<String>>>()
+        })
+        .find(|fields| {
+            let local_port = fields.get(1).and_then(|a| a.rsplit(':').next());
+            let local_port = local_port.and_then(|p| u16::from_str_radix(p, 16).ok());
+            local_port == Some(port) && fields.get(3).map(String::as_str) == Some(LISTEN)
+        })
+        .and_then(|fields| fields.get(9).cloned())?;
+    let socket = format!("socket:[{}]", inode);
+
+    for process in fs::read_dir("/proc").ok()?.filter_map(|entry| entry.ok()) {
+        let pid = process.file_name();
+        let Some(pid) = pid
+            .to_str()
+            .filter(|p| p.chars().all(|c| c.is_ascii_digit()))
+        else {
+            continue;
+        };
+        let Ok(descriptors) = fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        let holds_socket = descriptors
+            .filter_map(|fd| fd.ok())
+            .filter_map(|fd| fs::read_link(fd.path()).ok())
+            .any(|target| target.to_str() == Some(&socket));
+        if holds_socket {
+            let name = fs::read_to_string(process.path().join("comm")).unwrap_or_default();
+            return Some(format!("{} (pid {})", name.trim(), pid));
+        }
+    }
+    None
+}
+
+/// Returns the name and process id of the process listening on the given port,
+/// if it can be determined
+#[cfg(not(target_os = "linux"))]
+pub fn port_owner(_port: u16) -> Option<String> {
+    None
 }
 
 /// Returns the whether a git repository is dirty,
 /// that is, whether there are uncommitted changes
 /// TODO
 pub fn git_dirty(_directory: &str) -> bool {
-    return false;
+    false
+}
+
+/// Returns the commit the given project directory is at,
+/// or `None` if it is not a git repository
+pub fn git_commit(directory: &str) -> Option<String> {
+    let git_command = Command::new("git")
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(directory)
+        .output()
+        .ok()?;
+    if !git_command.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8_lossy(&git_command.stdout);
+    Some(commit.trim().to_string())
+}
+
+/// Returns how long ago the given time was, in seconds since
+/// the unix epoch, as a short human readable string
+pub fn format_age(timestamp: u64) -> String {
+    let seconds = crate::results::unix_time().saturating_sub(timestamp);
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3_599 => format!("{}m ago", seconds / 60),
+        3_600..=86_399 => format!("{}h ago", seconds / 3_600),
+        _ => format!("{}d ago", seconds / 86_400),
+    }
 }
 
 /// Returns the version of the current HEAD of the scaffolding in the given directory
@@ -567,6 +779,9 @@ pub fn current_scaffolding_version(directory: &str) -> String {
         return "".to_string();
     }
     let git_command = git_command.unwrap();
+    if !git_command.status.success() {
+        return "".to_string();
+    }
     let git_command = String::from_utf8_lossy(&git_command.stdout);
     git_command.trim().to_string()
 }
@@ -590,6 +805,9 @@ pub fn current_scaffolding_remote_version(directory: &str) -> String {
     }
 
     let git_command = git_command.unwrap();
+    if !git_command.status.success() {
+        return "".to_string();
+    }
     let git_command = String::from_utf8_lossy(&git_command.stdout);
 
     git_command.trim().to_string()
@@ -630,6 +848,7 @@ pub fn out_of_date_projects() -> Vec<String> {
 
     let out_of_date = recents
         .iter()
+        .filter(|x| Path::new(x).join("lib").join("scaffolding").exists())
         .filter(|x| {
             current_scaffolding_version(x) != current_scaffolding_remote_version(x) || git_dirty(x)
         })
@@ -655,17 +874,29 @@ pub fn update_out_of_date_projects() {
     info!("[+] All out of date projects updated!");
 }
 
-/// Check stourney out of date and warns the user if it is
-pub fn check_for_updates() {
-    let cargo_command = Command::new("cargo").arg("search").arg("stourney").output();
-    if cargo_command.is_err() {
+/// Returns the latest version of stourney published on crates.io,
+/// or None if it could not be found, such as when there is no network
+pub fn latest_version() -> Option<String> {
+    let cargo_command = Command::new("cargo")
+        .arg("search")
+        .arg("stourney")
+        .arg("--limit=1")
+        // Give up quickly rather than retrying on an air-gapped machine
+        .env("CARGO_HTTP_TIMEOUT", "10")
+        .env("CARGO_NET_RETRY", "0")
+        .output();
+    let Ok(cargo_command) = cargo_command else {
         warn!("[-] Failed to check for updates to stourney");
-        return;
+        return None;
+    };
+    if !cargo_command.status.success() {
+        warn!("[-] Failed to check for updates to stourney");
+        return None;
     }
-    let cargo_command = cargo_command.unwrap();
     let cargo_command = String::from_utf8_lossy(&cargo_command.stdout);
-    let version_string = format!("stourney = \"{}\"", VERSION);
-    if !cargo_command.contains(&version_string) {
-        println!("WARNING: stourney is out of date! Run `cargo install stourney` to update!");
-    }
+    let line = cargo_command
+        .lines()
+        .find_map(|line| line.strip_prefix("stourney = \""))?;
+    let version = line.split('"').next()?;
+    Some(version.to_string())
 }
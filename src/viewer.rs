@@ -0,0 +1,208 @@
+//! Contains a terminal viewer that steps through a replay move by move,
+//! for when the browser frontend cannot be opened
+use crate::replays::{self, Card, Position, Replay, Tokens, COLORS};
+use dialoguer::console::{style, Color, Key, StyledObject, Term};
+use std::io;
+
+/// The letter used for each gem color, onyx uses K to tell it apart from blue
+const LETTERS: [&str; 6] = ["W", "B", "G", "R", "K", "Y"];
+
+/// The terminal color used for each gem color
+const TERMINAL_COLORS: [Color; 6] = [
+    Color::White,
+    Color::Blue,
+    Color::Green,
+    Color::Red,
+    Color::Black,
+    Color::Yellow,
+];
+
+/// How many moves the larger steps skip
+const JUMP: usize = 10;
+
+/// Shows the replay in the terminal until the user quits
+pub fn view(replay: &Replay) -> io::Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the replay viewer needs an interactive terminal",
+        ));
+    }
+    if replay.positions.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the replay has no recorded positions",
+        ));
+    }
+
+    let last = replay.positions.len() - 1;
+    let mut index = 0;
+    term.hide_cursor()?;
+    let shown = loop {
+        term.clear_screen()?;
+        if let Err(e) = term.write_str(&render(replay, index)) {
+            break Err(e);
+        }
+        let key = match term.read_key() {
+            Ok(key) => key,
+            Err(e) => break Err(e),
+        };
+        index = match key {
+            Key::ArrowRight | Key::Char('l') | Key::Char(' ') | Key::Enter => (index + 1).min(last),
+            Key::ArrowLeft | Key::Char('h') | Key::Backspace => index.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => (index + JUMP).min(last),
+            Key::ArrowUp | Key::Char('k') => index.saturating_sub(JUMP),
+            Key::Home | Key::Char('g') => 0,
+            Key::End | Key::Char('G') => last,
+            Key::Escape | Key::Char('q') => break Ok(()),
+            _ => index,
+        };
+    };
+    term.clear_screen()?;
+    term.show_cursor()?;
+    shown
+}
+
+/// Returns the whole screen for the position after the move at `index`
+fn render(replay: &Replay, index: usize) -> String {
+    let position = &replay.positions[index];
+    let mut lines = Vec::new();
+    lines.push(format!(
+        "{}  seed {}  {}",
+        style(format!("Replay {}", replay.id)).bold(),
+        replay.seed,
+        replay.time_control
+    ));
+
+    let (moved, description) = replay.describe(index);
+    let player = match moved {
+        Some(seat) => replay.name(seat),
+        None => "-".to_string(),
+    };
+    let timing = match replay.moves.get(index) {
+        Some(played) => format!("  ({:.2}s, {:.1}s left)", played.think_time, played.clock),
+        None => String::new(),
+    };
+    lines.push(format!(
+        "Move {}/{}: {} {}{}",
+        index + 1,
+        replay.positions.len(),
+        style(player).bold(),
+        description,
+        style(timing).dim()
+    ));
+    lines.push(String::new());
+
+    lines.extend(render_board(position));
+    lines.push(String::new());
+    for seat in 0..position.players.len() {
+        lines.extend(render_player(replay, position, seat, moved == Some(seat)));
+        lines.push(String::new());
+    }
+
+    if index + 1 == replay.positions.len() {
+        let winner = match replay.winner {
+            Some(seat) => format!("{} wins", replay.name(seat)),
+            None => "Draw".to_string(),
+        };
+        lines.push(format!("{}  {}", style("Game over!").bold(), winner));
+    }
+    lines.push(
+        style("[←/h] back  [→/l] forward  [↑/k ↓/j] 10 moves  [g/G] start/end  [q] quit")
+            .dim()
+            .to_string(),
+    );
+    lines.join("\n")
+}
+
+/// Returns the lines showing the nobles, the bank and every tier of cards
+fn render_board(position: &Position) -> Vec<String> {
+    let mut lines = Vec::new();
+    let nobles: Vec<String> = position
+        .nobles
+        .iter()
+        .map(|noble| format!("[{}]", tokens(noble, true)))
+        .collect();
+    lines.push(format!("Nobles  {}", nobles.join(" ")));
+    lines.push(format!("Bank    {}", tokens(&position.bank, false)));
+
+    for tier in (0..position.cards.len()).rev() {
+        let left = position
+            .decks
+            .iter()
+            .find(|deck| deck.tier == tier)
+            .map_or(0, |deck| deck.cards);
+        let cards: Vec<String> = position.cards[tier].iter().map(card).collect();
+        lines.push(format!(
+            "Tier {} ({:>2} left)  {}",
+            tier + 1,
+            left,
+            cards.join(" ")
+        ));
+    }
+    lines
+}
+
+/// Returns the lines showing a single player, marking the player that just moved
+fn render_player(replay: &Replay, position: &Position, seat: usize, moved: bool) -> Vec<String> {
+    let player = &position.players[seat];
+    let marker = if moved { ">" } else { " " };
+    let nobles = match player.noble_points {
+        0 => String::new(),
+        points => format!(" ({} from nobles)", points),
+    };
+    let mut lines = vec![format!(
+        "{} {}  {} points{}",
+        marker,
+        style(replay.name(seat)).bold(),
+        player.points,
+        nobles
+    )];
+    lines.push(format!(
+        "    Cards     {}",
+        tokens(&player.developments, false)
+    ));
+    lines.push(format!("    Gems      {}", tokens(&player.gems, false)));
+    if !player.reserved.is_empty() {
+        let reserved: Vec<String> = player.reserved.iter().map(card).collect();
+        lines.push(format!("    Reserved  {}", reserved.join(" ")));
+    }
+    lines
+}
+
+/// Returns a colored gem letter
+fn letter(color: usize) -> StyledObject<&'static str> {
+    let letter = style(LETTERS[color]).fg(TERMINAL_COLORS[color]);
+    // Black text is unreadable on most dark terminals
+    if COLORS[color] == "black" {
+        letter.bright()
+    } else {
+        letter
+    }
+}
+
+/// Returns a list of tokens, either compact or with a column per color
+fn tokens(tokens: &Tokens, compact: bool) -> String {
+    if compact {
+        let counts: Vec<String> = tokens
+            .iter()
+            .map(|&(color, n)| format!("{}{}", n, letter(color)))
+            .collect();
+        return counts.join(" ");
+    }
+    let counts: Vec<String> = (0..COLORS.len())
+        .map(|color| format!("{} {:>2}", letter(color), replays::count(tokens, color)))
+        .collect();
+    counts.join("  ")
+}
+
+/// Returns a card as its points and color, followed by its cost
+fn card(card: &Card) -> String {
+    format!(
+        "[{}{}|{}]",
+        card.points,
+        letter(card.color),
+        tokens(&card.cost, true)
+    )
+}